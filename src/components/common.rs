@@ -1,5 +1,7 @@
 use bevy::prelude::{Component, Deref, DerefMut};
 
+use super::StatusEffect;
+
 #[derive(Debug, Component, Deref, DerefMut)]
 pub struct Health(pub i32);
 
@@ -12,8 +14,61 @@ impl Health {
     // }
 }
 
+#[derive(Debug, Component, Deref, DerefMut)]
+pub struct MaxHealth(pub i32);
+
+/// Heals an actor by one every `every` turns it takes.
+#[derive(Debug, Component)]
+pub struct NaturalRegen {
+    pub every: u32,
+    pub turns: u32,
+}
+
+impl NaturalRegen {
+    pub fn every(turns: u32) -> Self {
+        Self {
+            every: turns,
+            turns: 0,
+        }
+    }
+}
+
+/// What casting spells costs.
+#[derive(Debug, Component, Deref, DerefMut)]
+pub struct Mana(pub i32);
+
+#[derive(Debug, Component, Deref, DerefMut)]
+pub struct MaxMana(pub i32);
+
+/// Mana regained every round.
+#[derive(Debug, Component, Deref, DerefMut)]
+pub struct ManaRegen(pub i32);
+
+/// How often an actor gets to act, 100 is once every turn and 50 every other turn.
+/// Actors never act more than once a turn, so anything above 100 acts like 100.
+#[derive(Debug, Component, Deref, DerefMut)]
+pub struct Speed(pub u32);
+
+/// How an actor fights in melee.
+#[derive(Debug, Clone, Component)]
+pub struct MeleeAttack {
+    /// Chance to hit, out of 100.
+    pub accuracy: u32,
+    /// The least and most damage a hit deals.
+    pub damage: (i32, i32),
+    /// Applied to whatever is hit.
+    pub inflicts: Option<StatusEffect>,
+}
+
+/// Taken off all physical damage.
+#[derive(Debug, Component, Deref, DerefMut)]
+pub struct Armour(pub i32);
+
 #[derive(Debug, Component)]
 pub struct HealthText;
 
+#[derive(Debug, Component)]
+pub struct ManaText;
+
 // #[derive(Component, Debug, Deref, DerefMut)]
 // pub struct Velocity(pub bevy::math::Vec2);
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use bevy::prelude::{Component, Deref, DerefMut};
+use serde::Deserialize;
+
+/// What kind of harm something does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Fire,
+    Frost,
+    Poison,
+    Holy,
+}
+
+/// How an actor takes a type of damage.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Resistance {
+    /// Takes this fraction less, 0.25 takes three quarters.
+    Resistant(f32),
+    /// Takes this fraction more.
+    Vulnerable(f32),
+    /// Takes none at all.
+    Immune,
+}
+
+/// How an actor takes each type of damage, types not listed are taken in full.
+#[derive(Debug, Clone, Default, Component, Deref, DerefMut, Deserialize)]
+pub struct Resistances(pub HashMap<DamageType, Resistance>);
+
+impl Resistance {
+    /// The amount taken from a hit of `amount`.
+    pub fn apply(&self, amount: i32) -> i32 {
+        match self {
+            Resistance::Resistant(fraction) => (amount as f32 * (1. - fraction)).round() as i32,
+            Resistance::Vulnerable(fraction) => (amount as f32 * (1. + fraction)).round() as i32,
+            Resistance::Immune => 0,
+        }
+        .max(0)
+    }
+}
+
+impl Resistances {
+    /// The amount taken from a hit of `amount` of a type.
+    pub fn apply(&self, kind: DamageType, amount: i32) -> i32 {
+        self.get(&kind)
+            .map_or(amount, |resistance| resistance.apply(amount))
+    }
+}
@@ -0,0 +1,28 @@
+use bevy::prelude::Component;
+use serde::Deserialize;
+
+/// Who an actor sides with. Actors without one are neutral.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Component, Deserialize)]
+pub enum Faction {
+    /// The player and their allies.
+    Player,
+    Monsters,
+    /// Sides with no one, and no one sides with it.
+    #[default]
+    Neutral,
+}
+
+impl Faction {
+    /// Whether two actors are on the same side. Neutral actors are only on their own side.
+    pub fn allied_with(&self, other: Faction) -> bool {
+        *self == other && *self != Faction::Neutral
+    }
+
+    /// Whether actors of this faction go after actors of the other on sight.
+    pub fn hostile_to(&self, other: Faction) -> bool {
+        matches!(
+            (self, other),
+            (Faction::Player, Faction::Monsters) | (Faction::Monsters, Faction::Player)
+        )
+    }
+}
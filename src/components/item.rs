@@ -0,0 +1,25 @@
+use bevy::prelude::{Component, Deref, DerefMut, Entity};
+use serde::Deserialize;
+
+/// Something that can be picked up.
+#[derive(Debug, Component)]
+pub struct Item {
+    pub name: String,
+}
+
+/// The items an actor is carrying.
+#[derive(Debug, Component, Default, Deref, DerefMut)]
+pub struct Inventory(pub Vec<Entity>);
+
+/// A chance of dropping an item.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LootDrop {
+    /// Name of the item.
+    pub item: String,
+    /// From 0 to 1.
+    pub chance: f32,
+}
+
+/// What an actor may drop when it dies.
+#[derive(Debug, Clone, Default, Component, Deref, DerefMut, Deserialize)]
+pub struct LootTable(pub Vec<LootDrop>);
@@ -1,9 +1,19 @@
 //! Core components of the game.
 
 mod common;
+mod damage;
 mod enemy;
+mod faction;
+mod item;
 mod player;
+mod ranged;
+mod status;
 
 pub use common::*;
+pub use damage::*;
 pub use enemy::*;
+pub use faction::*;
+pub use item::*;
 pub use player::*;
+pub use ranged::*;
+pub use status::*;
@@ -5,8 +5,5 @@ use bevy_ecs_tilemap::TilePos;
 #[derive(Debug, Component, Default)]
 pub struct Player;
 
-#[derive(Debug, Component)]
-pub struct Blob;
-
 #[derive(Debug, Component, Clone, Copy, Deref, DerefMut, Default)]
 pub struct PassiveTilePos(pub TilePos);
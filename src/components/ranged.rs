@@ -0,0 +1,32 @@
+use bevy::prelude::{Color, Component, Deref};
+use serde::Deserialize;
+
+use super::{DamageType, StatusEffect};
+
+/// Something fired or thrown, which stops at the first wall or actor in its way.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Projectile {
+    /// The least and most damage a hit deals.
+    pub damage: (i32, i32),
+    #[serde(default)]
+    pub kind: DamageType,
+    /// How many tiles it flies.
+    pub range: u32,
+    /// How many actors it passes through after hitting them.
+    #[serde(default)]
+    pub pierce: u32,
+    /// Applied to everything it hits.
+    #[serde(default)]
+    pub inflicts: Option<StatusEffect>,
+    /// What it looks like in flight.
+    #[serde(default = "white")]
+    pub color: Color,
+}
+
+fn white() -> Color {
+    Color::WHITE
+}
+
+/// How an actor attacks from a distance.
+#[derive(Debug, Clone, Component, Deref)]
+pub struct RangedAttack(pub Projectile);
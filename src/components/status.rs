@@ -0,0 +1,222 @@
+use bevy::prelude::{Component, Deref, DerefMut, Entity};
+use serde::Deserialize;
+use strum::EnumString;
+
+/// A lasting effect on an actor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum StatusKind {
+    /// Takes its intensity in fire damage every turn.
+    Burning,
+    /// Takes its intensity in poison damage every turn.
+    Poisoned,
+    /// Acts half as often.
+    Slowed,
+    /// Loses its turns.
+    Stunned,
+    /// Heals its intensity every turn.
+    Regenerating,
+    /// Goes unnoticed by enemies.
+    Invisible,
+    /// Absorbs its intensity in damage, and is used up by it.
+    Shielded,
+}
+
+impl StatusKind {
+    /// How this kind stacks unless told otherwise.
+    pub fn stacking(&self) -> Stacking {
+        match self {
+            StatusKind::Poisoned | StatusKind::Shielded => Stacking::Intensify,
+            StatusKind::Stunned => Stacking::Ignore,
+            _ => Stacking::Refresh,
+        }
+    }
+}
+
+/// What happens when an effect is applied to an actor that already has one of its kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum Stacking {
+    /// Keep the longest duration and the highest intensity.
+    Refresh,
+    /// Add the intensities together and keep the longest duration.
+    Intensify,
+    /// Keep the effect that is already there.
+    Ignore,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Turns left before it wears off.
+    pub turns: u32,
+    #[serde(default = "one")]
+    pub intensity: i32,
+    /// Overrides the stacking of the kind.
+    #[serde(default)]
+    pub stacking: Option<Stacking>,
+    /// Who applied the effect, blamed for its damage.
+    #[serde(skip)]
+    pub source: Option<Entity>,
+}
+
+fn one() -> i32 {
+    1
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusKind, turns: u32, intensity: i32) -> Self {
+        Self {
+            kind,
+            turns,
+            intensity,
+            stacking: None,
+            source: None,
+        }
+    }
+
+    pub fn with_source(mut self, source: Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
+
+/// Every effect on an actor, at most one of each kind.
+#[derive(Debug, Clone, Default, Component, Deref, DerefMut)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    pub fn get(&self, kind: StatusKind) -> Option<&StatusEffect> {
+        self.iter().find(|effect| effect.kind == kind)
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    pub fn remove(&mut self, kind: StatusKind) {
+        self.retain(|effect| effect.kind != kind);
+    }
+
+    /// Adds an effect, following its stacking rule if there already is one of its kind.
+    pub fn apply(&mut self, effect: StatusEffect) {
+        let existing = match self
+            .iter_mut()
+            .find(|existing| existing.kind == effect.kind)
+        {
+            Some(existing) => existing,
+            None => {
+                self.push(effect);
+                return;
+            }
+        };
+
+        match effect.stacking.unwrap_or_else(|| effect.kind.stacking()) {
+            Stacking::Refresh => {
+                existing.intensity = existing.intensity.max(effect.intensity);
+                existing.turns = existing.turns.max(effect.turns);
+            }
+            Stacking::Intensify => {
+                existing.intensity += effect.intensity;
+                existing.turns = existing.turns.max(effect.turns);
+            }
+            Stacking::Ignore => return,
+        }
+
+        existing.source = effect.source.or(existing.source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with(effect: StatusEffect) -> StatusEffects {
+        StatusEffects(vec![effect])
+    }
+
+    #[test]
+    fn new_kinds_are_added() {
+        let mut effects = with(StatusEffect::new(StatusKind::Burning, 3, 2));
+        effects.apply(StatusEffect::new(StatusKind::Slowed, 2, 1));
+
+        assert_eq!(effects.len(), 2);
+        assert!(effects.has(StatusKind::Slowed));
+    }
+
+    #[test]
+    fn refresh_keeps_the_longest_and_strongest() {
+        let mut effects = with(StatusEffect::new(StatusKind::Burning, 3, 2));
+        effects.apply(StatusEffect::new(StatusKind::Burning, 5, 1));
+
+        let burning = effects.get(StatusKind::Burning).unwrap();
+        assert_eq!((burning.turns, burning.intensity), (5, 2));
+        assert_eq!(effects.len(), 1);
+
+        effects.apply(StatusEffect::new(StatusKind::Burning, 1, 4));
+
+        let burning = effects.get(StatusKind::Burning).unwrap();
+        assert_eq!((burning.turns, burning.intensity), (5, 4));
+    }
+
+    #[test]
+    fn intensify_adds_intensities() {
+        let mut effects = with(StatusEffect::new(StatusKind::Poisoned, 3, 2));
+        effects.apply(StatusEffect::new(StatusKind::Poisoned, 2, 3));
+
+        let poisoned = effects.get(StatusKind::Poisoned).unwrap();
+        assert_eq!((poisoned.turns, poisoned.intensity), (3, 5));
+    }
+
+    #[test]
+    fn ignore_keeps_the_existing_effect() {
+        let source = Entity::from_raw(1);
+        let mut effects = with(StatusEffect::new(StatusKind::Stunned, 1, 1));
+        effects.apply(StatusEffect::new(StatusKind::Stunned, 4, 3).with_source(source));
+
+        let stunned = effects.get(StatusKind::Stunned).unwrap();
+        assert_eq!((stunned.turns, stunned.intensity), (1, 1));
+        assert_eq!(stunned.source, None);
+    }
+
+    #[test]
+    fn stacking_of_the_effect_overrides_its_kind() {
+        // Burning refreshes unless told otherwise.
+        let mut effects = with(StatusEffect::new(StatusKind::Burning, 3, 2));
+        effects.apply(StatusEffect {
+            stacking: Some(Stacking::Intensify),
+            ..StatusEffect::new(StatusKind::Burning, 1, 2)
+        });
+
+        assert_eq!(effects.get(StatusKind::Burning).unwrap().intensity, 4);
+
+        // Poison intensifies unless told otherwise.
+        let mut effects = with(StatusEffect::new(StatusKind::Poisoned, 3, 2));
+        effects.apply(StatusEffect {
+            stacking: Some(Stacking::Ignore),
+            ..StatusEffect::new(StatusKind::Poisoned, 6, 2)
+        });
+
+        let poisoned = effects.get(StatusKind::Poisoned).unwrap();
+        assert_eq!((poisoned.turns, poisoned.intensity), (3, 2));
+    }
+
+    #[test]
+    fn stacking_blames_the_latest_source() {
+        let first = Entity::from_raw(1);
+        let second = Entity::from_raw(2);
+        let mut effects = with(StatusEffect::new(StatusKind::Burning, 3, 2).with_source(first));
+
+        effects.apply(StatusEffect::new(StatusKind::Burning, 3, 2).with_source(second));
+        assert_eq!(
+            effects.get(StatusKind::Burning).unwrap().source,
+            Some(second)
+        );
+
+        effects.apply(StatusEffect::new(StatusKind::Burning, 3, 2));
+        assert_eq!(
+            effects.get(StatusKind::Burning).unwrap().source,
+            Some(second)
+        );
+    }
+}
@@ -0,0 +1,490 @@
+//! Enemy AI, also driving the player's [`Ally`]s.
+//! Every enemy has a [`Behaviour`] describing its kind, and an [`Ai`] tracking what it is doing right now.
+//! Enemies act through [`ActionEvent`]s like the player, once per world turn, or on a timer in free roam.
+//! In a world turn they only act once the effects of the player's turn have played out.
+//! Anyone of a hostile [`Faction`] in view is a target, the closest one is gone after.
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::TilePos;
+use iyes_loopless::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::{
+    components::{
+        Faction, Health, Item, MaxHealth, PassiveTilePos, Player, RangedAttack, Speed,
+        StatusEffects, StatusKind,
+    },
+    map::{a_star, DijkstraMaps, FieldOfView, MapTiles},
+    util::offset_tile,
+    ActiveState, GameState,
+};
+
+use super::{
+    clear_shot, effects_finished, step_target, walk_cost, Action, ActionEvent, Ally, AllyOrder,
+    Awareness, AwarenessLevel, CutCornersConvar, Direction, TurnLabel, TurnState, WorldActed,
+    FOLLOW_DISTANCE, RANGED_AFFECTS,
+};
+
+/// How often the world acts while in free roam.
+const FREE_ROAM_TICK: Duration = Duration::from_millis(360);
+
+/// What an enemy does while it has not noticed the player.
+#[derive(Debug, Clone, Deserialize)]
+pub enum Idle {
+    /// Stand still at its post.
+    Guard,
+    /// Wander around randomly, never more than `range` tiles from its post.
+    Wander { range: u32 },
+    /// Walk between the waypoints in order, starting over after the last one.
+    /// Waypoints are given as offsets from the post.
+    Patrol(Vec<(i32, i32)>),
+}
+
+/// How a kind of enemy behaves.
+#[derive(Debug, Component, Clone, Deserialize)]
+pub struct Behaviour {
+    pub idle: Idle,
+    /// Chase and attack the player on sight. Allies always fight.
+    pub hostile: bool,
+    /// Flee from the player below this fraction of max health.
+    pub flee_below: Option<f32>,
+    /// Give up a chase when this many tiles from its post.
+    pub leash: Option<u32>,
+}
+
+/// What an enemy is currently doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiState {
+    /// Going about its [`Idle`] behaviour.
+    Idle,
+    /// Going after a target, heading to where it was last seen.
+    Chase { last_seen: TilePos },
+    /// Next to a target or with a clear shot at it, hitting it.
+    Attack(Entity),
+    /// Getting away from the player, making for the exit when it can get there first. Allies flee back to the player.
+    Flee,
+    /// Walking back to its post, which is next to the player for following allies.
+    Return,
+    /// Going to look at something it noticed, see [`Awareness`].
+    Investigate { at: TilePos },
+}
+
+#[derive(Debug, Component)]
+pub struct Ai {
+    pub state: AiState,
+    /// Where the enemy belongs, usually where it spawned.
+    pub post: TilePos,
+    /// The patrol waypoint being walked to.
+    waypoint: usize,
+    /// Saved up towards the next action, see [`Speed`].
+    energy: u32,
+}
+
+impl Ai {
+    pub fn new(post: TilePos) -> Self {
+        Self {
+            state: AiState::Idle,
+            post,
+            waypoint: 0,
+            energy: 0,
+        }
+    }
+}
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct WorldTickLabel;
+
+/// Counts down to the next time the world acts in free roam.
+#[derive(Debug, Deref, DerefMut)]
+pub struct WorldTick(Timer);
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WorldTick(Timer::new(FREE_ROAM_TICK, true)))
+            .add_system(
+                take_enemy_turns
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::TurnBased)
+                    .run_in_state(TurnState::World)
+                    .run_if(world_turn_pending)
+                    .run_if(effects_finished)
+                    .label(TurnLabel::Input),
+            )
+            .add_system(
+                tick_world
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::FreeRoam)
+                    .label(WorldTickLabel),
+            )
+            .add_system(
+                take_enemy_turns
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::FreeRoam)
+                    .run_if(world_ticked)
+                    .label(TurnLabel::Input)
+                    .after(WorldTickLabel),
+            );
+    }
+}
+
+fn tick_world(time: Res<Time>, mut tick: ResMut<WorldTick>) {
+    tick.tick(time.delta());
+}
+
+/// Whether the world has yet to act this world turn.
+fn world_turn_pending(acted: Res<WorldActed>) -> bool {
+    !**acted
+}
+
+/// Whether the world acts this frame in free roam.
+pub fn world_ticked(tick: Res<WorldTick>) -> bool {
+    tick.just_finished()
+}
+
+/// Tiles between two positions, counting a diagonal step as one.
+pub fn distance(a: TilePos, b: TilePos) -> u32 {
+    (a.0 as i32 - b.0 as i32)
+        .unsigned_abs()
+        .max((a.1 as i32 - b.1 as i32).unsigned_abs())
+}
+
+/// Whether two tiles are next to each other, diagonals included.
+pub fn adjacent(a: TilePos, b: TilePos) -> bool {
+    distance(a, b) == 1
+}
+
+/// The first step of the shortest walk to a tile.
+fn first_step(
+    tiles: &mut MapTiles,
+    from: TilePos,
+    goal: TilePos,
+    cut_corners: bool,
+) -> Option<TilePos> {
+    a_star(from, goal, |pos, direction| {
+        walk_cost(tiles, pos, direction, cut_corners)
+    })
+    .and_then(|path| path.first().copied())
+}
+
+/// Where a step ends up, if the terrain allows it and no one is standing there.
+fn free_step(
+    tiles: &mut MapTiles,
+    actors: &Query<&PassiveTilePos, Without<Item>>,
+    from: TilePos,
+    direction: Direction,
+    cut_corners: bool,
+) -> Option<TilePos> {
+    step_target(tiles, from, direction, cut_corners)
+        .filter(|target| !actors.iter().any(|actor| **actor == *target))
+}
+
+/// Decides on a state for every enemy and ally, then sends the action that state calls for.
+/// Anyone noticing a target in free roam starts turn based play.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn take_enemy_turns(
+    mut commands: Commands,
+    mut enemies: Query<
+        (
+            Entity,
+            &PassiveTilePos,
+            &FieldOfView,
+            &Health,
+            &MaxHealth,
+            &Behaviour,
+            &mut Ai,
+            Option<&Speed>,
+            Option<&StatusEffects>,
+            Option<&RangedAttack>,
+            Option<&mut Awareness>,
+            Option<&Faction>,
+            Option<&mut Ally>,
+        ),
+        Without<Player>,
+    >,
+    player_query: Query<&PassiveTilePos, With<Player>>,
+    targets: Query<
+        (
+            Entity,
+            &PassiveTilePos,
+            Option<&Faction>,
+            Option<&StatusEffects>,
+        ),
+        With<Health>,
+    >,
+    actors: Query<&PassiveTilePos, Without<Item>>,
+    mut tiles: MapTiles,
+    maps: Res<DijkstraMaps>,
+    cut_corners: Res<CutCornersConvar>,
+    game_state: Res<CurrentState<GameState>>,
+    mut acted: ResMut<WorldActed>,
+    mut actions: EventWriter<ActionEvent>,
+) {
+    **acted = true;
+
+    let player_pos = match player_query.get_single() {
+        Ok(pos) => **pos,
+        Err(_) => return,
+    };
+
+    let mut rng = rand::thread_rng();
+
+    for (
+        entity,
+        pos,
+        fov,
+        health,
+        max_health,
+        behaviour,
+        mut ai,
+        speed,
+        effects,
+        ranged,
+        mut awareness,
+        faction,
+        mut ally,
+    ) in enemies.iter_mut()
+    {
+        let has = |kind| effects.map_or(false, |effects: &StatusEffects| effects.has(kind));
+
+        // Slow enemies skip turns while saving up.
+        let mut gained = speed.map_or(100, |speed| **speed);
+        if has(StatusKind::Slowed) {
+            gained /= 2;
+        }
+
+        ai.energy += gained;
+        if ai.energy < 100 {
+            continue;
+        }
+        ai.energy = (ai.energy - 100).min(99);
+
+        if has(StatusKind::Stunned) {
+            actions.send(ActionEvent {
+                actor: entity,
+                action: Action::Wait,
+            });
+            continue;
+        }
+
+        let pos = **pos;
+        let faction = faction.copied().unwrap_or_default();
+
+        // An ally goes back to following once its target is gone.
+        if let Some(ally) = ally.as_mut() {
+            if let AllyOrder::Attack(target) = ally.order {
+                if targets.get(target).is_err() {
+                    ally.order = AllyOrder::Follow;
+                }
+            }
+        }
+
+        let ordered = ally.as_ref().and_then(|ally| match ally.order {
+            AllyOrder::Attack(target) => Some(target),
+            _ => None,
+        });
+        let following = ally
+            .as_ref()
+            .map_or(false, |ally| ally.order == AllyOrder::Follow);
+        if following {
+            ai.post = player_pos;
+        }
+
+        // Enemies without awareness notice their targets as soon as they see them.
+        let alert = awareness
+            .as_ref()
+            .map_or(true, |awareness| awareness.level() == AwarenessLevel::Alert);
+
+        // The ordered target comes first, then the closest one.
+        let target = targets
+            .iter()
+            .filter(|(other, other_pos, other_faction, other_effects)| {
+                *other != entity
+                    && faction.hostile_to(other_faction.copied().unwrap_or_default())
+                    && fov.tiles.contains(&**other_pos)
+                    && !other_effects.map_or(false, |effects| effects.has(StatusKind::Invisible))
+            })
+            .min_by_key(|(other, other_pos, _, _)| {
+                (Some(*other) != ordered, distance(pos, **other_pos))
+            })
+            .filter(|_| alert)
+            .map(|(other, other_pos, _, _)| (other, **other_pos));
+
+        // Whatever was noticed has been looked at once it is reached.
+        let mut investigate = None;
+        if let Some(awareness) = awareness.as_mut() {
+            if awareness.noticed == Some(pos) {
+                awareness.noticed = None;
+            }
+
+            if awareness.level() != AwarenessLevel::Unaware {
+                investigate = awareness.noticed;
+            }
+        }
+
+        let hurt = behaviour.flee_below.map_or(false, |below| {
+            (**health as f32) < below * **max_health as f32
+        });
+        let hostile = behaviour.hostile || ally.is_some();
+        let can_shoot = match (ranged, target) {
+            (Some(ranged), Some((_, target_pos))) => {
+                distance(pos, target_pos) <= ranged.range
+                    && clear_shot(
+                        &mut tiles,
+                        pos,
+                        target_pos,
+                        faction,
+                        RANGED_AFFECTS,
+                        |tile| {
+                            targets
+                                .iter()
+                                .find(|(_, other_pos, _, _)| ***other_pos == tile)
+                                .map(|(_, _, other_faction, _)| {
+                                    other_faction.copied().unwrap_or_default()
+                                })
+                        },
+                    )
+            }
+            _ => false,
+        };
+        let leashed = behaviour
+            .leash
+            .map_or(false, |leash| distance(pos, ai.post) > leash);
+        // Following allies only need to get close to the player.
+        let home = if following {
+            distance(pos, ai.post) <= FOLLOW_DISTANCE
+        } else {
+            pos == ai.post
+        };
+
+        let previous = ai.state;
+        ai.state = match (ai.state, target) {
+            // Once heading home targets are ignored until the post is reached.
+            (AiState::Return, _) if home => AiState::Idle,
+            (AiState::Return, _) => AiState::Return,
+            (_, Some(_)) if hurt => AiState::Flee,
+            (_, Some(_)) if hostile && leashed => AiState::Return,
+            (_, Some((target, target_pos)))
+                if hostile && (adjacent(pos, target_pos) || can_shoot) =>
+            {
+                AiState::Attack(target)
+            }
+            (_, Some((_, target_pos))) if hostile => AiState::Chase {
+                last_seen: target_pos,
+            },
+            (AiState::Chase { last_seen }, _) if last_seen != pos && !leashed => {
+                AiState::Chase { last_seen }
+            }
+            (AiState::Attack(lost), _) => match targets.get(lost) {
+                Ok((_, lost_pos, _, _)) => AiState::Chase {
+                    last_seen: **lost_pos,
+                },
+                Err(_) => AiState::Return,
+            },
+            (AiState::Investigate { at }, _) if at != pos && !leashed => {
+                AiState::Investigate { at }
+            }
+            (AiState::Idle, _) => match investigate {
+                Some(at) => AiState::Investigate { at },
+                None => AiState::Idle,
+            },
+            _ => AiState::Return,
+        };
+
+        if ai.state != previous {
+            debug!("{entity:?} went from {previous:?} to {:?}", ai.state);
+        }
+
+        if previous == AiState::Idle
+            && matches!(ai.state, AiState::Chase { .. } | AiState::Attack(_))
+            && game_state.0 == GameState::FreeRoam
+        {
+            commands.insert_resource(NextState(GameState::TurnBased));
+        }
+
+        let step = match ai.state {
+            AiState::Attack(target) => {
+                let target_pos = targets.get(target).map_or(pos, |(_, pos, _, _)| **pos);
+                let action = if adjacent(pos, target_pos) {
+                    Action::Attack(target)
+                } else {
+                    Action::Fire(target_pos)
+                };
+
+                actions.send(ActionEvent {
+                    actor: entity,
+                    action,
+                });
+                continue;
+            }
+            // The shared map already leads to the player, other places need a path of their own.
+            AiState::Chase { last_seen } if last_seen == player_pos && ally.is_none() => {
+                maps.player.downhill(pos, |direction| {
+                    free_step(&mut tiles, &actors, pos, direction, cut_corners.on)
+                })
+            }
+            AiState::Chase { last_seen } => first_step(&mut tiles, pos, last_seen, cut_corners.on),
+            AiState::Return => first_step(&mut tiles, pos, ai.post, cut_corners.on),
+            AiState::Investigate { at } => first_step(&mut tiles, pos, at, cut_corners.on),
+            AiState::Flee if ally.is_some() => {
+                first_step(&mut tiles, pos, player_pos, cut_corners.on)
+            }
+            // Fleeing makes for the exit if it can be reached before the player gets there.
+            AiState::Flee => {
+                let escapes = match (maps.exit.get(pos), maps.exit.get(player_pos)) {
+                    (Some(own), Some(player)) => own < player,
+                    _ => false,
+                };
+                let map = if escapes { &maps.exit } else { &maps.safety };
+
+                map.downhill(pos, |direction| {
+                    free_step(&mut tiles, &actors, pos, direction, cut_corners.on)
+                })
+            }
+            AiState::Idle if following && distance(pos, ai.post) > FOLLOW_DISTANCE => {
+                first_step(&mut tiles, pos, ai.post, cut_corners.on)
+            }
+            AiState::Idle if ally.is_some() => None,
+            AiState::Idle => match &behaviour.idle {
+                Idle::Guard => None,
+                Idle::Wander { range } => {
+                    let direction = *Direction::ALL.choose(&mut rng).unwrap();
+
+                    step_target(&mut tiles, pos, direction, cut_corners.on)
+                        .filter(|target| rng.gen_bool(0.5) && distance(*target, ai.post) <= *range)
+                }
+                Idle::Patrol(waypoints) if !waypoints.is_empty() => {
+                    let waypoint = |index: usize| {
+                        let (x, y) = waypoints[index % waypoints.len()];
+                        offset_tile(&ai.post, IVec2::new(x, y))
+                    };
+
+                    if waypoint(ai.waypoint) == Some(pos) {
+                        ai.waypoint = (ai.waypoint + 1) % waypoints.len();
+                    }
+
+                    waypoint(ai.waypoint)
+                        .and_then(|waypoint| first_step(&mut tiles, pos, waypoint, cut_corners.on))
+                }
+                Idle::Patrol(_) => None,
+            },
+        };
+
+        let action = step
+            .and_then(|target| {
+                Direction::from_offset(IVec2::new(
+                    target.0 as i32 - pos.0 as i32,
+                    target.1 as i32 - pos.1 as i32,
+                ))
+            })
+            .map_or(Action::Wait, Action::Move);
+
+        actions.send(ActionEvent {
+            actor: entity,
+            action,
+        });
+    }
+}
@@ -0,0 +1,247 @@
+//! Creatures fighting for the player.
+//! Allies are spawned from enemy definitions on the player's side, and act through the same [`Ai`] as enemies.
+//! They take orders through the `order` command, and the ones following the player come along to the next level.
+use bevy::prelude::*;
+use bevy_console::{reply, reply_failed, AddConsoleCommand, ConsoleCommand};
+use bevy_easings::EasingComponent;
+use bevy_ecs_tilemap::TilePos;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    components::{Faction, Health, PassiveTilePos, Player},
+    map::{FieldOfView, Floor, Room, StartingRoom},
+    util::trans_from_tile,
+    GameState,
+};
+
+use super::{distance, Ai, AiState, TileCursor};
+
+/// How many tiles from the player a following ally keeps to.
+pub const FOLLOW_DISTANCE: u32 = 2;
+
+/// What an ally was told to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AllyOrder {
+    /// Stay close to the player, fighting anything hostile that comes into view.
+    #[default]
+    Follow,
+    /// Hold where it is, only leaving it to fight.
+    Stay,
+    /// Go after one actor first, following again once it is gone.
+    Attack(Entity),
+}
+
+/// An actor fighting for the player.
+#[derive(Debug, Default, Component)]
+pub struct Ally {
+    pub order: AllyOrder,
+}
+
+/// An ally coming along to the level being generated.
+#[derive(Debug, Component)]
+pub struct Travelling;
+
+/// Orders every ally to follow, stay, or attack the hovered actor
+#[derive(ConsoleCommand)]
+#[console_command(name = "order")]
+struct OrderCommand {
+    /// follow, stay or attack
+    order: String,
+}
+
+pub struct AllyPlugin;
+
+impl Plugin for AllyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command::<OrderCommand, _, _>(order_command)
+            .add_enter_system(GameState::GeneratingMap, leave_level)
+            .add_exit_system(GameState::GeneratingMap, arrive_with_player);
+    }
+}
+
+fn order_command(
+    mut order: ConsoleCommand<OrderCommand>,
+    mut allies: Query<(&mut Ally, &mut Ai, &PassiveTilePos)>,
+    cursor: Query<&TileCursor, With<Player>>,
+    actors: Query<(Entity, &PassiveTilePos, Option<&Faction>), With<Health>>,
+) {
+    if let Some(OrderCommand { order: name }) = order.take() {
+        let given = match name.as_str() {
+            "follow" => AllyOrder::Follow,
+            "stay" => AllyOrder::Stay,
+            "attack" => {
+                let hovered = cursor.get_single().ok().and_then(|cursor| **cursor);
+                let target = actors.iter().find(|(_, pos, faction)| {
+                    Some(***pos) == hovered
+                        && Faction::Player.hostile_to(faction.copied().unwrap_or_default())
+                });
+
+                match target {
+                    Some((target, _, _)) => AllyOrder::Attack(target),
+                    None => {
+                        reply_failed!(order, "Hover over something hostile to attack");
+                        return;
+                    }
+                }
+            }
+            _ => {
+                reply_failed!(order, "No order '{name}', try follow, stay or attack");
+                return;
+            }
+        };
+
+        let mut count = 0;
+        for (mut ally, mut ai, pos) in allies.iter_mut() {
+            ally.order = given;
+            count += 1;
+
+            // Staying is done where the order was heard.
+            if given == AllyOrder::Stay {
+                ai.post = **pos;
+                ai.state = AiState::Return;
+            }
+        }
+
+        reply!(order, "Gave {count} allies the order to {name}");
+        order.ok();
+    }
+}
+
+/// Allies following the player within sight come along to the next level, the rest are left behind for good.
+fn leave_level(
+    mut commands: Commands,
+    allies: Query<(Entity, &Ally, &PassiveTilePos)>,
+    player: Query<&FieldOfView, With<Player>>,
+) {
+    let fov = player.get_single().ok();
+
+    for (entity, ally, pos) in allies.iter() {
+        let along =
+            ally.order != AllyOrder::Stay && fov.map_or(false, |fov| fov.tiles.contains(&**pos));
+
+        if along {
+            commands.entity(entity).insert(Travelling);
+        } else {
+            debug!("{entity:?} was left behind");
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Places the allies that came along on the floor closest to the player in the starting room.
+#[allow(clippy::type_complexity)]
+fn arrive_with_player(
+    mut commands: Commands,
+    mut allies: Query<
+        (
+            Entity,
+            &mut Ally,
+            &mut Ai,
+            &mut PassiveTilePos,
+            &mut Transform,
+        ),
+        With<Travelling>,
+    >,
+    rooms: Query<&Room, With<StartingRoom>>,
+    floors: Query<&TilePos, With<Floor>>,
+) {
+    let room = match rooms.get_single() {
+        Ok(room) => room,
+        Err(_) => return,
+    };
+
+    // The player is placed at the center of the room.
+    let center = room.center();
+    let player = TilePos(center.x as u32, center.y as u32);
+
+    let mut free = floors
+        .iter()
+        .copied()
+        .filter(|pos| room.contains(pos.0, pos.1) && *pos != player)
+        .collect::<Vec<_>>();
+    free.sort_by_key(|pos| (distance(*pos, player), pos.0, pos.1));
+
+    let mut free = free.into_iter();
+    for (entity, mut ally, mut ai, mut pos, mut transform) in allies.iter_mut() {
+        commands
+            .entity(entity)
+            .remove::<Travelling>()
+            .remove::<EasingComponent<Transform>>();
+
+        let arrived = match free.next() {
+            Some(arrived) => arrived,
+            None => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        // Whoever they were after stayed on the old level.
+        ally.order = AllyOrder::Follow;
+        *ai = Ai::new(arrived);
+        **pos = arrived;
+        transform.translation = trans_from_tile(&arrived).extend(transform.translation.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::TileRect;
+
+    use super::*;
+
+    fn spawn_ally(app: &mut App, pos: TilePos, order: AllyOrder) -> Entity {
+        app.world
+            .spawn()
+            .insert(Ally { order })
+            .insert(Ai::new(pos))
+            .insert(PassiveTilePos(pos))
+            .insert(Transform::from_translation(
+                trans_from_tile(&pos).extend(1.),
+            ))
+            .id()
+    }
+
+    #[test]
+    fn following_allies_come_along_and_staying_ones_are_left_behind() {
+        let mut app = App::new();
+        app.add_loopless_state(GameState::FreeRoam)
+            .add_enter_system(GameState::GeneratingMap, leave_level)
+            .add_exit_system(GameState::GeneratingMap, arrive_with_player);
+
+        let following = spawn_ally(&mut app, TilePos(20, 20), AllyOrder::Follow);
+        let staying = spawn_ally(&mut app, TilePos(21, 20), AllyOrder::Stay);
+        app.world.spawn().insert(Player).insert(FieldOfView {
+            range: 4,
+            tiles: vec![TilePos(20, 20), TilePos(21, 20)],
+        });
+
+        app.insert_resource(NextState(GameState::GeneratingMap));
+        app.update();
+
+        assert!(app.world.get::<Travelling>(following).is_some());
+        assert!(app.world.get_entity(staying).is_none());
+
+        // The new level, with its starting room centered on (2, 2).
+        app.world
+            .spawn()
+            .insert(Room(TileRect::new(0, 0, 4, 4)))
+            .insert(StartingRoom);
+        for x in 0..=4 {
+            for y in 0..=4 {
+                app.world.spawn().insert(TilePos(x, y)).insert(Floor);
+            }
+        }
+
+        app.insert_resource(NextState(GameState::FreeRoam));
+        app.update();
+
+        let pos = **app.world.get::<PassiveTilePos>(following).unwrap();
+        assert_eq!(distance(pos, TilePos(2, 2)), 1);
+        assert!(app.world.get::<Travelling>(following).is_none());
+        assert_eq!(
+            app.world.get::<Ally>(following).unwrap().order,
+            AllyOrder::Follow
+        );
+    }
+}
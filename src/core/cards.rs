@@ -0,0 +1,205 @@
+//! Spells as cards, drawn from a deck into a hand and played from there.
+//! The hand is filled up on first entering a room, and another card is drawn every round of turn based play.
+use bevy::prelude::*;
+use bevy_console::{reply, reply_failed, AddConsoleCommand, ConsoleCommand};
+use iyes_loopless::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    components::{Inventory, Item, PassiveTilePos, Player},
+    map::Room,
+    ActiveState, GameState,
+};
+
+use super::{SpellDef, SpellDefs, TurnState};
+
+/// The most cards a hand holds.
+pub const HAND_SIZE: usize = 5;
+/// Cards drawn at the start of every turn of turn based play.
+const DRAWS_PER_TURN: usize = 1;
+
+/// The spell cards an actor owns, by name.
+#[derive(Debug, Clone, Default, Component)]
+pub struct Deck {
+    pub draw_pile: Vec<String>,
+    pub hand: Vec<String>,
+    pub discard: Vec<String>,
+    /// Index in the hand of the card about to be played.
+    pub readied: Option<usize>,
+}
+
+impl Deck {
+    /// A deck with the cards shuffled into the draw pile.
+    pub fn new(cards: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let mut draw_pile = cards.into_iter().map(Into::into).collect::<Vec<_>>();
+        draw_pile.shuffle(&mut rand::thread_rng());
+
+        Self {
+            draw_pile,
+            ..Default::default()
+        }
+    }
+
+    /// Draws up to `count` cards, never holding more than [`HAND_SIZE`].
+    /// The discard pile is shuffled back in once the draw pile runs out.
+    pub fn draw(&mut self, count: usize) {
+        for _ in 0..count {
+            if self.hand.len() >= HAND_SIZE {
+                return;
+            }
+
+            if self.draw_pile.is_empty() {
+                self.draw_pile.append(&mut self.discard);
+                self.draw_pile.shuffle(&mut rand::thread_rng());
+            }
+
+            match self.draw_pile.pop() {
+                Some(card) => self.hand.push(card),
+                None => return,
+            }
+        }
+    }
+
+    pub fn fill_hand(&mut self) {
+        self.draw(HAND_SIZE.saturating_sub(self.hand.len()));
+    }
+
+    /// Moves a card from the hand to the discard pile.
+    pub fn play(&mut self, index: usize) -> Option<String> {
+        if index >= self.hand.len() {
+            return None;
+        }
+
+        let card = self.hand.remove(index);
+        self.discard.push(card.clone());
+        self.readied = None;
+
+        Some(card)
+    }
+
+    /// Shuffles a new card into the draw pile.
+    pub fn add(&mut self, card: impl Into<String>) {
+        let at = rand::thread_rng().gen_range(0..=self.draw_pile.len());
+        self.draw_pile.insert(at, card.into());
+    }
+
+    pub fn readied_card(&self) -> Option<&str> {
+        self.readied
+            .and_then(|index| self.hand.get(index))
+            .map(String::as_str)
+    }
+}
+
+/// A room the player has already filled their hand in.
+#[derive(Debug, Component)]
+pub struct DrawnIn;
+
+/// Adds a spell card to the player's deck
+#[derive(ConsoleCommand)]
+#[console_command(name = "learn")]
+struct LearnCommand {
+    /// Name of the spell
+    name: String,
+}
+
+pub struct CardPlugin;
+
+impl Plugin for CardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command::<LearnCommand, _, _>(learn_command)
+            .add_enter_system(TurnState::Player, draw_each_turn)
+            .add_system(
+                draw_on_entering_room
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap),
+            )
+            .add_system(
+                collect_card_rewards
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap),
+            );
+    }
+}
+
+fn draw_each_turn(
+    mut decks: Query<&mut Deck, With<Player>>,
+    game_state: Res<CurrentState<GameState>>,
+) {
+    if game_state.0 != GameState::TurnBased {
+        return;
+    }
+
+    for mut deck in decks.iter_mut() {
+        deck.draw(DRAWS_PER_TURN);
+    }
+}
+
+fn draw_on_entering_room(
+    mut commands: Commands,
+    mut player: Query<(&PassiveTilePos, &mut Deck), (With<Player>, Changed<PassiveTilePos>)>,
+    rooms: Query<(Entity, &Room), Without<DrawnIn>>,
+) {
+    let (pos, mut deck) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    if let Some((room, _)) = rooms.iter().find(|(_, room)| room.contains(pos.0, pos.1)) {
+        deck.fill_hand();
+        commands.entity(room).insert(DrawnIn);
+    }
+}
+
+/// Picked up items named after a spell are cards, and go into the deck.
+fn collect_card_rewards(
+    mut commands: Commands,
+    mut player: Query<(&mut Inventory, &mut Deck), (With<Player>, Changed<Inventory>)>,
+    items: Query<&Item>,
+    defs: Res<SpellDefs>,
+    assets: Res<Assets<SpellDef>>,
+) {
+    let (mut inventory, mut deck) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    let cards = inventory
+        .iter()
+        .filter_map(|entity| items.get(*entity).ok().map(|item| (*entity, item)))
+        .filter(|(_, item)| defs.find(&item.name, &assets).is_some())
+        .collect::<Vec<_>>();
+
+    // Only touch the inventory when there is a card, it would count as changed again otherwise.
+    if cards.is_empty() {
+        return;
+    }
+
+    for (entity, item) in cards {
+        info!("Added a {} card to the deck", item.name);
+        deck.add(item.name.clone());
+        inventory.retain(|carried| *carried != entity);
+        commands.entity(entity).despawn();
+    }
+}
+
+fn learn_command(
+    mut learn: ConsoleCommand<LearnCommand>,
+    mut decks: Query<&mut Deck, With<Player>>,
+    defs: Res<SpellDefs>,
+    assets: Res<Assets<SpellDef>>,
+) {
+    if let Some(LearnCommand { name }) = learn.take() {
+        if defs.find(&name, &assets).is_none() {
+            let names = defs.names(&assets).collect::<Vec<_>>().join(", ");
+            reply_failed!(learn, "No spell named '{name}', try one of: {names}");
+            return;
+        }
+
+        if let Ok(mut deck) = decks.get_single_mut() {
+            deck.add(name.clone());
+            reply!(learn, "Shuffled a {name} card into the deck");
+        }
+
+        learn.ok();
+    }
+}
@@ -1,31 +1,193 @@
 use bevy::prelude::*;
 use bevy_ecs_tilemap::TilePos;
+use leafwing_input_manager::prelude::ActionState;
 
-use super::{mouse::CurrentMousePosition, Spell};
+use crate::{
+    components::{Inventory, Item, Mana, PassiveTilePos, Player},
+    map::MapTiles,
+};
 
-#[derive(Debug, Clone, Copy)]
+use super::{
+    mouse::CurrentMousePosition, Action, ActionPerformed, CastRefusal, CountPrefix, Deck, Hotbar,
+    HotbarAction, HotbarSlot, SpellCharges, SpellDef, SpellDefs,
+};
+
+/// Consumable items that cast a spell once, for free, are named this followed by the spell.
+pub const SCROLL_PREFIX: &str = "scroll of ";
+
+#[derive(Debug, Clone)]
 pub struct SpellCast {
+    pub caster: Entity,
     pub position: TilePos,
-    pub spell: Spell,
+    pub spell: Handle<SpellDef>,
+}
+
+/// A spell could not be cast, for the UI to tell why.
+#[derive(Debug, Clone)]
+pub struct CastRefused {
+    pub caster: Entity,
+    pub spell: String,
+    pub reason: CastRefusal,
 }
 
+/// Uses the hotbar slots. A card is readied by the first press of its slot and played at the hovered tile by the second.
+/// Items are used right away. Either spends the player's turn.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn cast_spell(
-    // should take inventory, only fireball for now
-    input: Res<Input<KeyCode>>,
+    mut commands: Commands,
     mut writer: EventWriter<SpellCast>,
+    mut refusals: EventWriter<CastRefused>,
+    mut performed: EventWriter<ActionPerformed>,
     hovered: Res<CurrentMousePosition>,
+    mut player: Query<
+        (
+            Entity,
+            &PassiveTilePos,
+            &ActionState<HotbarAction>,
+            &Hotbar,
+            &mut Deck,
+            &mut Inventory,
+            Option<&mut Mana>,
+            Option<&mut SpellCharges>,
+        ),
+        (With<Player>, Without<CountPrefix>),
+    >,
+    items: Query<&Item>,
+    defs: Res<SpellDefs>,
+    assets: Res<Assets<SpellDef>>,
+    mut tiles: MapTiles,
 ) {
-    if let Some(hovered) = **hovered {
-        if input.just_pressed(KeyCode::Key1) {
-            writer.send(SpellCast {
-                position: hovered,
-                spell: Spell::Fireball,
-            })
-        } else if input.just_pressed(KeyCode::Key2) {
+    // The number keys are typing in a count while there is a prefix.
+    let (caster, pos, action_state, hotbar, mut deck, mut inventory, mana, charges) =
+        match player.get_single_mut() {
+            Ok(player) => player,
+            Err(_) => return,
+        };
+
+    let slot = match HotbarAction::ALL
+        .into_iter()
+        .find(|action| action_state.just_pressed(*action))
+    {
+        Some(action) => &hotbar[action.slot()],
+        None => return,
+    };
+
+    let mut refuse = |spell: &str, reason: CastRefusal| {
+        info!("Can't cast {spell}, {reason}");
+        refusals.send(CastRefused {
+            caster,
+            spell: spell.to_string(),
+            reason,
+        });
+    };
+
+    let index = match slot {
+        HotbarSlot::Empty => return,
+        HotbarSlot::Card(index) if *index < deck.hand.len() => *index,
+        HotbarSlot::Card(_) => return,
+        HotbarSlot::Spell(name) => match deck.hand.iter().position(|card| card == name) {
+            Some(index) => index,
+            None => {
+                refuse(name, CastRefusal::NotInHand);
+                return;
+            }
+        },
+        HotbarSlot::Item(name) => {
+            let item = inventory
+                .iter()
+                .copied()
+                .find(|entity| items.get(*entity).map_or(false, |item| item.name == *name));
+
+            let item = match item {
+                Some(item) => item,
+                None => {
+                    info!("Not carrying any {name}");
+                    return;
+                }
+            };
+
+            let (spell, def) = match name
+                .strip_prefix(SCROLL_PREFIX)
+                .and_then(|spell| defs.find(spell, &assets))
+            {
+                Some(found) => found,
+                None => {
+                    info!("{name} can't be used");
+                    return;
+                }
+            };
+
+            let hovered = match **hovered {
+                Some(hovered) => hovered,
+                None => return,
+            };
+
+            if let Err(reason) = def.check_target(**pos, hovered, &mut tiles) {
+                refuse(&def.name, reason);
+                return;
+            }
+
+            inventory.retain(|carried| *carried != item);
+            commands.entity(item).despawn();
+
             writer.send(SpellCast {
+                caster,
                 position: hovered,
-                spell: Spell::Heal,
-            })
+                spell,
+            });
+            performed.send(ActionPerformed {
+                actor: caster,
+                action: Action::Cast,
+            });
+            return;
         }
+    };
+
+    // The first press readies the card, showing the area of its spell under the cursor.
+    if deck.readied != Some(index) {
+        deck.readied = Some(index);
+        return;
     }
+
+    let hovered = match **hovered {
+        Some(hovered) => hovered,
+        None => return,
+    };
+
+    let name = &deck.hand[index];
+    let (spell, def) = match defs.find(name, &assets) {
+        Some(found) => found,
+        None => {
+            warn!("No spell named '{name}'");
+            return;
+        }
+    };
+
+    // A refused card stays readied, to be aimed somewhere else or tried again later.
+    let checked = def
+        .check_target(**pos, hovered, &mut tiles)
+        .and_then(|_| def.check_resources(mana.as_deref(), charges.as_deref()));
+
+    if let Err(reason) = checked {
+        refuse(&def.name, reason);
+        return;
+    }
+
+    deck.play(index);
+    if let Some(mut mana) = mana {
+        **mana -= def.cost as i32;
+    }
+    if let Some(mut charges) = charges {
+        charges.spend(def);
+    }
+
+    writer.send(SpellCast {
+        caster,
+        position: hovered,
+        spell,
+    });
+    performed.send(ActionPerformed {
+        actor: caster,
+        action: Action::Cast,
+    });
 }
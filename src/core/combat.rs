@@ -0,0 +1,554 @@
+//! Melee combat, damage, healing and death.
+//! Anything that changes health sends a [`DamageEvent`] or [`HealEvent`], nothing else touches [`Health`].
+//! What actually happened is published as [`DamageDealt`] and [`Healed`], and a [`Died`] once health runs out.
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use rand::Rng;
+
+use crate::{
+    components::{
+        Armour, DamageType, Health, LootTable, MaxHealth, MeleeAttack, PassiveTilePos, Player,
+        Resistances, StatusEffects, StatusKind,
+    },
+    ActiveState, GameState,
+};
+
+use super::{
+    adjacent, Action, ActionEvent, ActionPerformed, ApplyStatus, Awareness, AwarenessLevel,
+    SpawnItem, TurnLabel,
+};
+
+/// Something should take damage. Armour and [`Resistances`] are applied before it is.
+#[derive(Debug, Clone, Copy)]
+pub struct DamageEvent {
+    /// Who dealt the damage, if anyone.
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: i32,
+    pub kind: DamageType,
+}
+
+/// Something should be healed, never above its [`MaxHealth`].
+#[derive(Debug, Clone, Copy)]
+pub struct HealEvent {
+    /// Who did the healing, if anyone.
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: i32,
+}
+
+/// Damage that was taken, after armour and resistances.
+#[derive(Debug, Clone, Copy)]
+pub struct DamageDealt {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub kind: DamageType,
+    /// The damage before armour and resistances.
+    pub rolled: i32,
+    /// The damage that was taken.
+    pub amount: i32,
+    /// Whether the target was unaware of the hit coming.
+    pub sneak_attack: bool,
+    /// Whether this took the last of the target's health.
+    pub killed: bool,
+}
+
+/// Health that was restored.
+#[derive(Debug, Clone, Copy)]
+pub struct Healed {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    /// Only what was restored, healing at full health is 0.
+    pub amount: i32,
+}
+
+/// An actor's health ran out.
+#[derive(Debug, Clone, Copy)]
+pub struct Died {
+    pub entity: Entity,
+    /// Who dealt the final blow, if anyone.
+    pub killer: Option<Entity>,
+}
+
+/// How many times the damage a hit on an unaware enemy deals.
+const SNEAK_ATTACK_MULTIPLIER: i32 = 2;
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct DamageLabel;
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<HealEvent>()
+            .add_event::<DamageDealt>()
+            .add_event::<Healed>()
+            .add_event::<Died>()
+            .add_system(
+                resolve_attacks
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(TurnLabel::Resolve)
+                    .after(TurnLabel::Input),
+            )
+            .add_system(
+                apply_damage
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(DamageLabel)
+                    .after(TurnLabel::Resolve),
+            )
+            .add_system(
+                apply_healing
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(DamageLabel)
+                    .after(TurnLabel::Resolve),
+            )
+            .add_system(
+                handle_deaths
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .after(DamageLabel),
+            );
+    }
+}
+
+/// Rolls to hit with the attacker's [`MeleeAttack`], then for physical damage.
+/// The turn is spent whether the attack hits or not.
+fn resolve_attacks(
+    mut actions: EventReader<ActionEvent>,
+    mut performed: EventWriter<ActionPerformed>,
+    mut damage: EventWriter<DamageEvent>,
+    mut statuses: EventWriter<ApplyStatus>,
+    attackers: Query<(&PassiveTilePos, &MeleeAttack)>,
+    targets: Query<&PassiveTilePos, With<Health>>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for event in actions.iter() {
+        let target = match event.action {
+            Action::Attack(target) => target,
+            _ => continue,
+        };
+
+        let (pos, melee) = match attackers.get(event.actor) {
+            Ok(attacker) => attacker,
+            Err(_) => continue,
+        };
+
+        let target_pos = match targets.get(target) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+
+        if !adjacent(**pos, **target_pos) {
+            continue;
+        }
+
+        performed.send(ActionPerformed {
+            actor: event.actor,
+            action: event.action,
+        });
+
+        if rng.gen_range(0..100) >= melee.accuracy {
+            debug!("{:?} missed {target:?}", event.actor);
+            continue;
+        }
+
+        let (least, most) = melee.damage;
+        damage.send(DamageEvent {
+            source: Some(event.actor),
+            target,
+            amount: rng.gen_range(least..=most.max(least)),
+            kind: DamageType::Physical,
+        });
+
+        if let Some(effect) = melee.inflicts {
+            statuses.send(ApplyStatus {
+                target,
+                effect: effect.with_source(event.actor),
+            });
+        }
+    }
+}
+
+/// Multiplies hits on unaware targets, takes [`Armour`] off physical damage, then applies [`Resistances`],
+/// and lets a shield absorb what it can, before taking it from the target's health.
+fn apply_damage(
+    mut events: EventReader<DamageEvent>,
+    mut dealt: EventWriter<DamageDealt>,
+    mut died: EventWriter<Died>,
+    mut targets: Query<(
+        &mut Health,
+        Option<&Armour>,
+        Option<&Resistances>,
+        Option<&mut StatusEffects>,
+        Option<&Awareness>,
+    )>,
+) {
+    // Several hits in one frame still only kill once.
+    let mut killed = HashSet::new();
+
+    for event in events.iter() {
+        let (mut health, armour, resistances, effects, awareness) =
+            match targets.get_mut(event.target) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+
+        let sneak_attack = event.source.is_some()
+            && awareness.map_or(false, |awareness| {
+                awareness.level() == AwarenessLevel::Unaware
+            });
+
+        let mut amount = event.amount;
+        if sneak_attack {
+            amount *= SNEAK_ATTACK_MULTIPLIER;
+        }
+        if event.kind == DamageType::Physical {
+            amount -= armour.map_or(0, |armour| **armour);
+        }
+        amount = resistances
+            .map_or(amount, |resistances| resistances.apply(event.kind, amount))
+            .max(0);
+
+        if let Some(mut effects) = effects {
+            if let Some(shield) = effects
+                .iter_mut()
+                .find(|effect| effect.kind == StatusKind::Shielded)
+            {
+                let absorbed = amount.min(shield.intensity);
+                shield.intensity -= absorbed;
+                amount -= absorbed;
+
+                if shield.intensity <= 0 {
+                    effects.remove(StatusKind::Shielded);
+                }
+            }
+        }
+
+        let was_alive = **health > 0;
+        **health -= amount;
+        let kills = was_alive && **health <= 0 && killed.insert(event.target);
+
+        if sneak_attack {
+            info!("Sneak attack on {:?}", event.target);
+        }
+
+        debug!(
+            "{:?} took {amount} {:?} damage from {:?} ({} before armour and resistances)",
+            event.target, event.kind, event.source, event.amount
+        );
+
+        dealt.send(DamageDealt {
+            source: event.source,
+            target: event.target,
+            kind: event.kind,
+            rolled: event.amount,
+            amount,
+            sneak_attack,
+            killed: kills,
+        });
+
+        if kills {
+            died.send(Died {
+                entity: event.target,
+                killer: event.source,
+            });
+        }
+    }
+}
+
+fn apply_healing(
+    mut events: EventReader<HealEvent>,
+    mut healed: EventWriter<Healed>,
+    mut targets: Query<(&mut Health, Option<&MaxHealth>)>,
+) {
+    for event in events.iter() {
+        if let Ok((mut health, max_health)) = targets.get_mut(event.target) {
+            let before = **health;
+            **health = (before + event.amount.max(0))
+                .min(max_health.map_or(i32::MAX, |max_health| **max_health))
+                .max(before);
+
+            healed.send(Healed {
+                source: event.source,
+                target: event.target,
+                amount: **health - before,
+            });
+        }
+    }
+}
+
+/// Dead actors drop their loot and are removed, the player dying ends the game.
+fn handle_deaths(
+    mut commands: Commands,
+    mut died: EventReader<Died>,
+    player: Query<(), With<Player>>,
+    actors: Query<(&PassiveTilePos, Option<&LootTable>)>,
+    mut items: EventWriter<SpawnItem>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for Died { entity, .. } in died.iter() {
+        if player.contains(*entity) {
+            info!("You died");
+            commands.insert_resource(NextState(ActiveState::GameOver));
+            continue;
+        }
+
+        if let Ok((pos, loot)) = actors.get(*entity) {
+            for drop in loot.iter().flat_map(|loot| loot.iter()) {
+                if rng.gen_bool(drop.chance.clamp(0., 1.) as f64) {
+                    items.send(SpawnItem {
+                        name: drop.item.clone(),
+                        pos: **pos,
+                    });
+                }
+            }
+        }
+
+        commands.entity(*entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bevy::ecs::world::EntityMut;
+
+    use crate::components::{Resistance, StatusEffect};
+
+    use super::*;
+
+    fn hit(target: Entity, amount: i32, kind: DamageType) -> DamageEvent {
+        DamageEvent {
+            source: None,
+            target,
+            amount,
+            kind,
+        }
+    }
+
+    /// Runs [`apply_damage`] once on a target spawned from `components`, with every hit in the same frame.
+    fn damage(
+        components: impl FnOnce(&mut EntityMut),
+        hits: impl FnOnce(Entity) -> Vec<DamageEvent>,
+    ) -> (App, Entity, Vec<DamageDealt>) {
+        let mut app = App::new();
+        app.add_event::<DamageEvent>()
+            .add_event::<DamageDealt>()
+            .add_event::<Died>()
+            .add_system(apply_damage);
+
+        let mut target = app.world.spawn();
+        target.insert(Health(100));
+        components(&mut target);
+        let target = target.id();
+
+        for event in hits(target) {
+            app.world.resource_mut::<Events<DamageEvent>>().send(event);
+        }
+        app.update();
+
+        let events = app.world.resource::<Events<DamageDealt>>();
+        let dealt = events.get_reader().iter(events).copied().collect();
+
+        (app, target, dealt)
+    }
+
+    fn resistant(kind: DamageType, resistance: Resistance) -> Resistances {
+        Resistances(HashMap::from([(kind, resistance)]))
+    }
+
+    #[test]
+    fn armour_only_reduces_physical_damage() {
+        let (app, target, dealt) = damage(
+            |target| {
+                target.insert(Armour(3));
+            },
+            |target| {
+                vec![
+                    hit(target, 10, DamageType::Physical),
+                    hit(target, 10, DamageType::Fire),
+                ]
+            },
+        );
+
+        assert_eq!(dealt[0].amount, 7);
+        assert_eq!(dealt[0].rolled, 10);
+        assert_eq!(dealt[1].amount, 10);
+        assert_eq!(**app.world.get::<Health>(target).unwrap(), 83);
+    }
+
+    #[test]
+    fn armour_never_heals() {
+        let (app, target, dealt) = damage(
+            |target| {
+                target.insert(Armour(5));
+            },
+            |target| vec![hit(target, 2, DamageType::Physical)],
+        );
+
+        assert_eq!(dealt[0].amount, 0);
+        assert_eq!(**app.world.get::<Health>(target).unwrap(), 100);
+    }
+
+    #[test]
+    fn resistant_targets_take_less() {
+        let (_, _, dealt) = damage(
+            |target| {
+                target.insert(resistant(DamageType::Fire, Resistance::Resistant(0.5)));
+            },
+            |target| {
+                vec![
+                    hit(target, 10, DamageType::Fire),
+                    hit(target, 10, DamageType::Frost),
+                ]
+            },
+        );
+
+        assert_eq!(dealt[0].amount, 5);
+        assert_eq!(dealt[1].amount, 10);
+    }
+
+    #[test]
+    fn vulnerable_targets_take_more() {
+        let (_, _, dealt) = damage(
+            |target| {
+                target.insert(resistant(DamageType::Holy, Resistance::Vulnerable(0.5)));
+            },
+            |target| vec![hit(target, 10, DamageType::Holy)],
+        );
+
+        assert_eq!(dealt[0].amount, 15);
+    }
+
+    #[test]
+    fn immune_targets_take_nothing() {
+        let (app, target, dealt) = damage(
+            |target| {
+                target.insert(resistant(DamageType::Poison, Resistance::Immune));
+            },
+            |target| vec![hit(target, 50, DamageType::Poison)],
+        );
+
+        assert_eq!(dealt[0].amount, 0);
+        assert_eq!(**app.world.get::<Health>(target).unwrap(), 100);
+    }
+
+    #[test]
+    fn resistance_applies_after_armour() {
+        let (_, _, dealt) = damage(
+            |target| {
+                target
+                    .insert(Armour(2))
+                    .insert(resistant(DamageType::Physical, Resistance::Resistant(0.5)));
+            },
+            |target| vec![hit(target, 10, DamageType::Physical)],
+        );
+
+        assert_eq!(dealt[0].amount, 4);
+    }
+
+    #[test]
+    fn shield_absorbs_damage_until_used_up() {
+        let shield =
+            |intensity| StatusEffects(vec![StatusEffect::new(StatusKind::Shielded, 10, intensity)]);
+
+        let (app, target, dealt) = damage(
+            |target| {
+                target.insert(shield(20));
+            },
+            |target| vec![hit(target, 5, DamageType::Fire)],
+        );
+        let effects = app.world.get::<StatusEffects>(target).unwrap();
+
+        assert_eq!(dealt[0].amount, 0);
+        assert_eq!(effects.get(StatusKind::Shielded).unwrap().intensity, 15);
+
+        let (app, target, dealt) = damage(
+            |target| {
+                target.insert(shield(4));
+            },
+            |target| vec![hit(target, 10, DamageType::Fire)],
+        );
+        let effects = app.world.get::<StatusEffects>(target).unwrap();
+
+        assert_eq!(dealt[0].amount, 6);
+        assert!(!effects.has(StatusKind::Shielded));
+        assert_eq!(**app.world.get::<Health>(target).unwrap(), 94);
+    }
+
+    #[test]
+    fn unaware_targets_take_double_damage_from_an_attacker() {
+        let (_, _, dealt) = damage(
+            |target| {
+                target.insert(Awareness::default()).insert(Armour(1));
+            },
+            |target| {
+                vec![
+                    DamageEvent {
+                        source: Some(target),
+                        ..hit(target, 5, DamageType::Physical)
+                    },
+                    // Nobody to be caught unaware by.
+                    hit(target, 5, DamageType::Physical),
+                ]
+            },
+        );
+
+        // Doubled before armour is taken off.
+        assert_eq!(dealt[0].amount, 9);
+        assert!(dealt[0].sneak_attack);
+        assert_eq!(dealt[1].amount, 4);
+        assert!(!dealt[1].sneak_attack);
+    }
+
+    #[test]
+    fn alert_targets_take_normal_damage() {
+        let (_, _, dealt) = damage(
+            |target| {
+                target.insert(Awareness {
+                    // Fully alert.
+                    alertness: 100,
+                    noticed: None,
+                });
+            },
+            |target| {
+                vec![DamageEvent {
+                    source: Some(target),
+                    ..hit(target, 5, DamageType::Physical)
+                }]
+            },
+        );
+
+        assert_eq!(dealt[0].amount, 5);
+        assert!(!dealt[0].sneak_attack);
+    }
+
+    #[test]
+    fn targets_die_only_once_per_frame() {
+        let (app, _, dealt) = damage(
+            |target| {
+                target.insert(Health(5));
+            },
+            |target| {
+                vec![
+                    hit(target, 10, DamageType::Physical),
+                    hit(target, 10, DamageType::Physical),
+                ]
+            },
+        );
+        let died = app.world.resource::<Events<Died>>();
+
+        assert!(dealt[0].killed);
+        assert!(!dealt[1].killed);
+        assert_eq!(died.get_reader().iter(died).count(), 1);
+    }
+}
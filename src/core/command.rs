@@ -0,0 +1,25 @@
+//! Actions the player can take that are not movement.
+use bevy::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerPlugin, Actionlike};
+use strum::EnumString;
+
+#[derive(Actionlike, Debug, Component, Clone, Copy, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum CommandAction {
+    /// Walk to the nearest unexplored part of the map.
+    AutoExplore,
+    /// Do nothing for a turn.
+    Wait,
+    /// Wait until healed.
+    Rest,
+    /// Look around for anything hidden nearby.
+    Search,
+}
+
+pub struct CommandPlugin;
+
+impl Plugin for CommandPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(InputManagerPlugin::<CommandAction>::default());
+    }
+}
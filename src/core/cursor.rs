@@ -3,12 +3,12 @@ use bevy_ecs_tilemap::{MapQuery, TilePos};
 use iyes_loopless::prelude::*;
 
 use crate::{
-    components::Player,
-    map::{Floor, TilePaint},
+    components::{Health, PassiveTilePos, Player},
+    map::{Floor, MapTiles, TilePaint},
     ActiveState, GameState,
 };
 
-use super::mouse::CurrentMousePosition;
+use super::{mouse::CurrentMousePosition, Deck, SpellDef, SpellDefs};
 
 #[derive(Debug, Deref, DerefMut, Component, Clone, Copy)]
 pub struct TileCursor(Option<TilePos>);
@@ -52,6 +52,10 @@ impl TileCursor {
     }
 
     pub fn draw_circle(position: &TilePos, r: u32) -> Vec<TilePos> {
+        if r == 0 {
+            return vec![*position];
+        }
+
         let mut tiles = vec![];
         let mut i = 1;
         for y in (position.1.saturating_sub(r))..=(position.1.saturating_add(r)) {
@@ -82,20 +86,55 @@ impl Plugin for PlayerHoveredPlugin {
     }
 }
 
+/// Paints the hovered tile, or the area of the readied card's spell if there is one.
+/// The area is red when the spell can't be cast there.
+#[allow(clippy::type_complexity)]
 fn hovered_player_system(
-    mut player_query: Query<&mut TileCursor, With<Player>>,
+    mut player_query: Query<(Entity, &mut TileCursor, &PassiveTilePos, &Deck), With<Player>>,
+    actors: Query<(Entity, &PassiveTilePos), With<Health>>,
     mouse_position: Res<CurrentMousePosition>,
-    mut map: MapQuery,
+    mut map: MapTiles,
     mut tiles_query: Query<&mut TilePaint, With<Floor>>,
+    defs: Res<SpellDefs>,
+    assets: Res<Assets<SpellDef>>,
 ) {
-    let mut player_cursor = player_query.single_mut();
+    let (player, mut player_cursor, player_pos, deck) = player_query.single_mut();
 
     *player_cursor = TileCursor(**mouse_position);
 
-    for tile in player_cursor.circle(2).unwrap_or(vec![]) {
-        if let Ok(ent) = map.get_tile_entity(tile, 0, 0) {
+    let hovered = match **player_cursor {
+        Some(hovered) => hovered,
+        None => return,
+    };
+
+    let readied = deck
+        .readied_card()
+        .and_then(|name| defs.find(name, &assets));
+
+    let (area, color) = match readied {
+        Some((_, def)) => {
+            let others = actors
+                .iter()
+                .filter(|(entity, _)| *entity != player)
+                .map(|(_, pos)| **pos)
+                .collect::<Vec<_>>();
+            let color = match def.check_target(**player_pos, hovered, &mut map) {
+                Ok(()) => Color::GREEN,
+                Err(_) => Color::RED,
+            };
+
+            (
+                def.area.tiles(**player_pos, hovered, &others, &mut map),
+                color,
+            )
+        }
+        None => (vec![hovered], Color::GREEN),
+    };
+
+    for tile in area {
+        if let Some(ent) = map.entity(tile) {
             if let Ok(mut current) = tiles_query.get_mut(ent) {
-                *current = current.greater_of(TilePaint::CursorDraw(Color::GREEN));
+                *current = current.greater_of(TilePaint::CursorDraw(color));
             }
         }
     }
@@ -0,0 +1,328 @@
+//! Enemy definitions, loaded from `assets/enemies/*.enemy.ron`.
+//! Definitions are hot-reloaded, enemies that are already spawned pick up the changes.
+//! The same definitions are spawned as [`Ally`]s by summons, on the player's side.
+use bevy::{prelude::*, reflect::TypeUuid};
+use bevy_console::{reply, reply_failed, AddConsoleCommand, ConsoleCommand};
+use bevy_ecs_tilemap::TilePos;
+use iyes_loopless::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    components::{
+        Armour, Enemy, Faction, Health, LootTable, MaxHealth, MeleeAttack, PassiveTilePos, Player,
+        Projectile, RangedAttack, Resistances, Speed, StatusEffect,
+    },
+    map::FieldOfView,
+    util::{trans_from_tile, AssetFolder, NamedAsset, RonAsset, RonLoader},
+    GameState,
+};
+
+use super::{Ai, Ally, Awareness, Behaviour, TileCursor};
+
+/// A way an enemy can attack.
+#[derive(Debug, Clone, Deserialize)]
+pub enum AttackDef {
+    /// Hit an adjacent target, see [`MeleeAttack`].
+    Melee {
+        accuracy: u32,
+        damage: (i32, i32),
+        #[serde(default)]
+        inflicts: Option<StatusEffect>,
+    },
+    /// Fire a projectile at a target in a clear line, see [`RangedAttack`].
+    Ranged(Projectile),
+}
+
+/// Everything about a kind of enemy.
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "6f0a3f5e-7d0b-4c55-9a43-0c2a8d1f7e21"]
+pub struct EnemyDef {
+    /// What the enemy is spawned by.
+    pub name: String,
+    /// Path of the sprite, relative to the assets folder.
+    pub sprite: String,
+    pub health: i32,
+    /// See [`Speed`].
+    #[serde(default = "default_speed")]
+    pub speed: u32,
+    pub fov_range: u32,
+    pub attacks: Vec<AttackDef>,
+    #[serde(default)]
+    pub armour: i32,
+    pub behaviour: Behaviour,
+    #[serde(default = "default_faction")]
+    pub faction: Faction,
+    #[serde(default)]
+    pub resistances: Resistances,
+    #[serde(default)]
+    pub loot: LootTable,
+}
+
+fn default_speed() -> u32 {
+    100
+}
+
+fn default_faction() -> Faction {
+    Faction::Monsters
+}
+
+impl EnemyDef {
+    fn melee(&self) -> Option<MeleeAttack> {
+        self.attacks.iter().find_map(|attack| match attack {
+            AttackDef::Melee {
+                accuracy,
+                damage,
+                inflicts,
+            } => Some(MeleeAttack {
+                accuracy: *accuracy,
+                damage: *damage,
+                inflicts: *inflicts,
+            }),
+            _ => None,
+        })
+    }
+
+    fn ranged(&self) -> Option<RangedAttack> {
+        self.attacks.iter().find_map(|attack| match attack {
+            AttackDef::Ranged(projectile) => Some(RangedAttack(projectile.clone())),
+            _ => None,
+        })
+    }
+}
+
+impl RonAsset for EnemyDef {
+    const EXTENSIONS: &'static [&'static str] = &["enemy.ron"];
+}
+
+impl NamedAsset for EnemyDef {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Every enemy definition.
+pub type EnemyDefs = AssetFolder<EnemyDef>;
+
+/// Spawns an enemy by the name of its definition.
+#[derive(Debug, Clone)]
+pub struct SpawnEnemy {
+    pub name: String,
+    pub pos: TilePos,
+    /// Spawn it as an [`Ally`] of the player instead.
+    pub ally: bool,
+}
+
+/// Spawns an enemy
+#[derive(ConsoleCommand)]
+#[console_command(name = "spawn")]
+struct SpawnCommand {
+    /// Name of the enemy
+    name: String,
+    /// Tile to spawn on, the hovered tile if left out
+    x: Option<u32>,
+    y: Option<u32>,
+}
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<EnemyDef>()
+            .init_asset_loader::<RonLoader<EnemyDef>>()
+            .add_event::<SpawnEnemy>()
+            .add_startup_system(load_enemy_defs)
+            .add_console_command::<SpawnCommand, _, _>(spawn_command)
+            .add_system(spawn_enemies.run_not_in_state(GameState::GeneratingMap))
+            .add_system(reload_enemies);
+    }
+}
+
+fn load_enemy_defs(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(EnemyDefs::load(&asset_server, "enemies"));
+}
+
+fn spawn_command(
+    mut spawn: ConsoleCommand<SpawnCommand>,
+    mut writer: EventWriter<SpawnEnemy>,
+    cursor: Query<&TileCursor, With<Player>>,
+    defs: Res<EnemyDefs>,
+    assets: Res<Assets<EnemyDef>>,
+) {
+    if let Some(SpawnCommand { name, x, y }) = spawn.take() {
+        if defs.find(&name, &assets).is_none() {
+            let names = defs.names(&assets).collect::<Vec<_>>().join(", ");
+            reply_failed!(spawn, "No enemy named '{name}', try one of: {names}");
+            return;
+        }
+
+        let hovered = cursor.get_single().ok().and_then(|cursor| **cursor);
+        let pos = match (x, y) {
+            (Some(x), Some(y)) => TilePos(x, y),
+            _ => match hovered {
+                Some(pos) => pos,
+                None => {
+                    reply_failed!(spawn, "Give a position or hover over a tile");
+                    return;
+                }
+            },
+        };
+
+        reply!(spawn, "Spawning {name} at {pos:?}");
+        writer.send(SpawnEnemy {
+            name,
+            pos,
+            ally: false,
+        });
+        spawn.ok();
+    }
+}
+
+/// Spawns requested enemies once their definitions have loaded.
+fn spawn_enemies(
+    mut commands: Commands,
+    mut requests: EventReader<SpawnEnemy>,
+    mut pending: Local<Vec<SpawnEnemy>>,
+    defs: Res<EnemyDefs>,
+    assets: Res<Assets<EnemyDef>>,
+    asset_server: Res<AssetServer>,
+) {
+    pending.extend(requests.iter().cloned());
+
+    if pending.is_empty() || !defs.ready(&asset_server) {
+        return;
+    }
+
+    for SpawnEnemy { name, pos, ally } in pending.drain(..) {
+        let (handle, def) = match defs.find(&name, &assets) {
+            Some(found) => found,
+            None => {
+                warn!("No enemy named '{name}'");
+                continue;
+            }
+        };
+
+        let mut enemy = commands.spawn_bundle(SpriteBundle {
+            texture: asset_server.load(&def.sprite),
+            transform: Transform::from_translation(trans_from_tile(&pos).extend(1.0)),
+            ..Default::default()
+        });
+
+        enemy
+            .insert(handle)
+            .insert(PassiveTilePos(pos))
+            .insert(FieldOfView::new(def.fov_range))
+            .insert(Health(def.health))
+            .insert(MaxHealth(def.health))
+            .insert(Speed(def.speed))
+            .insert(def.behaviour.clone())
+            .insert(Ai::new(pos))
+            .insert(Armour(def.armour))
+            .insert(def.resistances.clone())
+            .insert(def.loot.clone());
+
+        // Allies don't sneak up on anyone, they are told where to go.
+        if ally {
+            enemy.insert(Ally::default()).insert(Faction::Player);
+        } else {
+            enemy
+                .insert(Enemy)
+                .insert(Awareness::default())
+                .insert(def.faction);
+        }
+
+        if let Some(melee) = def.melee() {
+            enemy.insert(melee);
+        }
+
+        if let Some(ranged) = def.ranged() {
+            enemy.insert(ranged);
+        }
+
+        debug!("Spawned {name} at {pos:?}");
+    }
+}
+
+/// Applies changes to a definition to every enemy spawned from it.
+#[allow(clippy::type_complexity)]
+fn reload_enemies(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<EnemyDef>>,
+    assets: Res<Assets<EnemyDef>>,
+    asset_server: Res<AssetServer>,
+    mut enemies: Query<(
+        Entity,
+        &Handle<EnemyDef>,
+        &mut Handle<Image>,
+        &mut PassiveTilePos,
+        &mut FieldOfView,
+        &mut Health,
+        &mut MaxHealth,
+        &mut Speed,
+        &mut Behaviour,
+        Option<&Ally>,
+    )>,
+) {
+    for event in events.iter() {
+        let changed = match event {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+
+        let def = match assets.get(changed) {
+            Some(def) => def,
+            None => continue,
+        };
+
+        info!("Reloaded enemy '{}'", def.name);
+
+        for (
+            entity,
+            handle,
+            mut texture,
+            mut pos,
+            mut fov,
+            mut health,
+            mut max_health,
+            mut speed,
+            mut behaviour,
+            ally,
+        ) in enemies.iter_mut()
+        {
+            if handle != changed {
+                continue;
+            }
+
+            *texture = asset_server.load(&def.sprite);
+            **max_health = def.health;
+            **health = (**health).min(def.health);
+            **speed = def.speed;
+            *behaviour = def.behaviour.clone();
+
+            if fov.range != def.fov_range {
+                fov.range = def.fov_range;
+                // What it sees is only recalculated when it moves.
+                pos.set_changed();
+            }
+
+            let mut enemy = commands.entity(entity);
+            if ally.is_none() {
+                enemy.insert(def.faction);
+            }
+
+            enemy
+                .insert(Armour(def.armour))
+                .insert(def.resistances.clone())
+                .insert(def.loot.clone());
+
+            match def.melee() {
+                Some(melee) => enemy.insert(melee),
+                None => enemy.remove::<MeleeAttack>(),
+            };
+
+            match def.ranged() {
+                Some(ranged) => enemy.insert(ranged),
+                None => enemy.remove::<RangedAttack>(),
+            };
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Auto-explore. Walks to the nearest unexplored part of the map, over and over, until something interesting happens.
+//! Searching turns up unexplored parts of the map close by that can't be seen, like a room behind a wall.
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::{MapQuery, Tile, TilePos};
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use rand::Rng;
+
+use crate::{
+    components::{Enemy, Health, Item, PassiveTilePos, Player},
+    map::{a_star, dijkstra, Explored, FieldOfView, MapTiles, TilePaint},
+    util::offset_tile,
+    ActiveState, GameState,
+};
+
+use super::{
+    explored_walk_cost, visible_hostiles, Action, ActionEvent, ActionPerformed, CommandAction,
+    CutCornersConvar, Direction, StopReason, Travel, TravelStopped, TurnLabel,
+};
+
+/// How many tiles away from the player a search reaches.
+const SEARCH_RANGE: i32 = 3;
+
+/// The chance a single search turns up any one tile in range, searching again finds more.
+const SEARCH_CHANCE: f64 = 1. / 3.;
+
+/// The player is exploring on their own.
+#[derive(Debug, Component)]
+pub struct AutoExplore {
+    /// Health when exploring started, losing any of it stops the exploration.
+    health: i32,
+}
+
+pub struct ExplorePlugin;
+
+impl Plugin for ExplorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(ActiveState::Playing)
+                .run_not_in_state(GameState::GeneratingMap)
+                .with_system(start_exploring)
+                .with_system(explore)
+                .with_system(stop_exploring_with_travel)
+                .into(),
+        )
+        .add_system(
+            resolve_search
+                .run_in_state(ActiveState::Playing)
+                .run_not_in_state(GameState::GeneratingMap)
+                .label(TurnLabel::Resolve)
+                .after(TurnLabel::Input),
+        );
+    }
+}
+
+/// Each unexplored tile near the player has a chance to be found, and is then shown as explored.
+/// Searching takes a turn whether anything is found or not.
+fn resolve_search(
+    mut commands: Commands,
+    mut actions: EventReader<ActionEvent>,
+    mut performed: EventWriter<ActionPerformed>,
+    player: Query<&PassiveTilePos, With<Player>>,
+    mut map: MapQuery,
+    mut tiles: Query<(&mut TilePaint, &mut Tile), Without<Explored>>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for event in actions.iter() {
+        if !matches!(event.action, Action::Search) {
+            continue;
+        }
+
+        if let Ok(pos) = player.get(event.actor) {
+            let mut found = 0;
+
+            for x in -SEARCH_RANGE..=SEARCH_RANGE {
+                for y in -SEARCH_RANGE..=SEARCH_RANGE {
+                    let tile = match offset_tile(pos, IVec2::new(x, y)) {
+                        Some(tile) => tile,
+                        None => continue,
+                    };
+                    let entity = match map.get_tile_entity(tile, 0, 0) {
+                        Ok(entity) => entity,
+                        Err(_) => continue,
+                    };
+
+                    if let Ok((mut paint, mut sprite)) = tiles.get_mut(entity) {
+                        if !rng.gen_bool(SEARCH_CHANCE) {
+                            continue;
+                        }
+
+                        *paint = paint.greater_of(TilePaint::PreviouslySeen);
+                        sprite.visible = true;
+                        sprite.color = Color::GRAY;
+                        map.notify_chunk_for_tile(tile, 0u16, 0u16);
+                        commands.entity(entity).insert(Explored);
+                        found += 1;
+                    }
+                }
+            }
+
+            if found > 0 {
+                info!("Found {found} tiles nearby");
+            }
+        }
+
+        performed.send(ActionPerformed {
+            actor: event.actor,
+            action: event.action,
+        });
+    }
+}
+
+fn stop_exploring(commands: &mut Commands, entity: Entity, reason: StopReason) {
+    info!("Stopped exploring: {reason}");
+    commands
+        .entity(entity)
+        .remove::<AutoExplore>()
+        .remove::<Travel>();
+}
+
+fn start_exploring(
+    mut commands: Commands,
+    player_query: Query<(Entity, &Health, &ActionState<CommandAction>), With<Player>>,
+) {
+    if let Ok((entity, health, action_state)) = player_query.get_single() {
+        if action_state.just_pressed(CommandAction::AutoExplore) {
+            commands
+                .entity(entity)
+                .remove::<Travel>()
+                .insert(AutoExplore { health: **health });
+        }
+    }
+}
+
+/// Any walk that ends without arriving also ends the exploration.
+fn stop_exploring_with_travel(
+    mut commands: Commands,
+    mut stopped: EventReader<TravelStopped>,
+    exploring: Query<(), With<AutoExplore>>,
+) {
+    for TravelStopped { entity, reason } in stopped.iter() {
+        if *reason != StopReason::Arrived && exploring.contains(*entity) {
+            stop_exploring(&mut commands, *entity, *reason);
+        }
+    }
+}
+
+/// Picks the next place to walk to whenever the player is not already walking somewhere.
+/// Items that have been seen are picked up before exploring further.
+fn explore(
+    mut commands: Commands,
+    player_query: Query<
+        (
+            Entity,
+            &PassiveTilePos,
+            &FieldOfView,
+            &Health,
+            &AutoExplore,
+            Option<&Travel>,
+        ),
+        With<Player>,
+    >,
+    hostiles: Query<(Entity, &PassiveTilePos), With<Enemy>>,
+    items: Query<&PassiveTilePos, With<Item>>,
+    mut tiles: MapTiles,
+    cut_corners: Res<CutCornersConvar>,
+) {
+    let (entity, pos, fov, health, explore, travel) = match player_query.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    if **health < explore.health {
+        stop_exploring(&mut commands, entity, StopReason::TookDamage);
+        return;
+    }
+
+    if travel.is_some() {
+        return;
+    }
+
+    if !visible_hostiles(fov, &hostiles).is_empty() {
+        stop_exploring(&mut commands, entity, StopReason::HostileInView);
+        return;
+    }
+
+    let costs = dijkstra(&[**pos], None, |from, direction| {
+        explored_walk_cost(&mut tiles, from, direction, cut_corners.on)
+    });
+
+    let target = nearest(&costs, |tile| items.iter().any(|item| **item == tile))
+        .or_else(|| nearest(&costs, |tile| is_frontier(&mut tiles, tile)));
+
+    let path = target.and_then(|target| {
+        a_star(**pos, target, |from, direction| {
+            explored_walk_cost(&mut tiles, from, direction, cut_corners.on)
+        })
+    });
+
+    match path {
+        Some(path) => {
+            commands.entity(entity).insert(Travel::new(path, vec![]));
+        }
+        None => stop_exploring(&mut commands, entity, StopReason::Explored),
+    }
+}
+
+/// The cheapest tile to get to that isn't the one the player is standing on.
+fn nearest(
+    costs: &HashMap<TilePos, u32>,
+    mut filter: impl FnMut(TilePos) -> bool,
+) -> Option<TilePos> {
+    costs
+        .iter()
+        .filter(|(tile, cost)| **cost > 0 && filter(**tile))
+        .min_by_key(|(_, cost)| **cost)
+        .map(|(tile, _)| *tile)
+}
+
+/// A tile next to part of the map that hasn't been seen yet.
+fn is_frontier(tiles: &mut MapTiles, pos: TilePos) -> bool {
+    Direction::ALL.into_iter().any(|direction| {
+        offset_tile(&pos, direction.offset()).map_or(false, |next| {
+            tiles.entity(next).is_some() && !tiles.is_explored(next)
+        })
+    })
+}
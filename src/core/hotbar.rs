@@ -0,0 +1,93 @@
+//! Hotbar slots, each holding a card of the hand, a spell or a consumable item.
+use bevy::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerPlugin, Actionlike};
+use strum::EnumString;
+
+#[derive(Actionlike, Debug, Component, Clone, Copy, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum HotbarAction {
+    Slot1,
+    Slot2,
+    Slot3,
+    Slot4,
+    Slot5,
+    Slot6,
+    Slot7,
+    Slot8,
+    Slot9,
+}
+
+impl HotbarAction {
+    pub const ALL: [HotbarAction; 9] = [
+        HotbarAction::Slot1,
+        HotbarAction::Slot2,
+        HotbarAction::Slot3,
+        HotbarAction::Slot4,
+        HotbarAction::Slot5,
+        HotbarAction::Slot6,
+        HotbarAction::Slot7,
+        HotbarAction::Slot8,
+        HotbarAction::Slot9,
+    ];
+
+    /// Index of the slot this action uses.
+    pub fn slot(&self) -> usize {
+        Self::ALL.iter().position(|action| action == self).unwrap()
+    }
+}
+
+/// What a hotbar slot uses.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HotbarSlot {
+    #[default]
+    Empty,
+    /// The card at this index of the hand.
+    Card(usize),
+    /// The first card in the hand for this spell.
+    Spell(String),
+    /// The first carried item with this name.
+    Item(String),
+}
+
+impl HotbarSlot {
+    /// `empty`, `card<n>` counting from 1, `item:<name>`, or otherwise the name of a spell.
+    pub fn parse(s: &str) -> Self {
+        let card = s
+            .strip_prefix("card")
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| *n > 0);
+
+        if s.eq_ignore_ascii_case("empty") {
+            HotbarSlot::Empty
+        } else if let Some(n) = card {
+            HotbarSlot::Card(n - 1)
+        } else if let Some(item) = s.strip_prefix("item:") {
+            HotbarSlot::Item(item.to_string())
+        } else {
+            HotbarSlot::Spell(s.to_string())
+        }
+    }
+}
+
+/// The slots of the hotbar, in the order of [`HotbarAction::ALL`].
+#[derive(Debug, Clone, Component, Deref, DerefMut)]
+pub struct Hotbar(pub [HotbarSlot; 9]);
+
+impl Default for Hotbar {
+    /// The first slots hold the cards of the hand.
+    fn default() -> Self {
+        let mut slots: [HotbarSlot; 9] = Default::default();
+        for (index, slot) in slots.iter_mut().take(super::HAND_SIZE).enumerate() {
+            *slot = HotbarSlot::Card(index);
+        }
+        Self(slots)
+    }
+}
+
+pub struct HotbarPlugin;
+
+impl Plugin for HotbarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(InputManagerPlugin::<HotbarAction>::default());
+    }
+}
@@ -0,0 +1,144 @@
+//! Mana and spell cooldowns.
+//! Mana comes back every round. Every cast spends a charge of the spell, and charges come back one at a time after its cooldown.
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    components::{Mana, ManaRegen, MaxMana},
+    ActiveState, GameState,
+};
+
+use super::{world_ticked, SpellDef, TurnLabel, TurnState};
+
+/// How long a spell takes to get a charge back.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Cooldown {
+    /// Rounds, which pass on the world timer in free roam.
+    Turns(u32),
+    /// Real time, paused along with the game.
+    Seconds(f32),
+}
+
+/// A spell that is getting its charges back.
+#[derive(Debug, Clone, Copy)]
+struct Recharge {
+    cooldown: Cooldown,
+    /// Charges that have been spent.
+    spent: u32,
+    /// Turns or seconds until the next charge is back.
+    left: f32,
+}
+
+/// The spells an actor is waiting on, by name.
+#[derive(Debug, Clone, Default, Component)]
+pub struct SpellCharges(HashMap<String, Recharge>);
+
+impl SpellCharges {
+    /// Charges of a spell that can be cast right now.
+    pub fn available(&self, def: &SpellDef) -> u32 {
+        let spent = self.0.get(&def.name).map_or(0, |recharge| recharge.spent);
+        def.charges.saturating_sub(spent)
+    }
+
+    /// How long until the next charge of a spell is back, if it is recharging.
+    pub fn ready_in(&self, def: &SpellDef) -> Option<Cooldown> {
+        self.0
+            .get(&def.name)
+            .map(|recharge| match recharge.cooldown {
+                Cooldown::Turns(_) => Cooldown::Turns(recharge.left.ceil() as u32),
+                Cooldown::Seconds(_) => Cooldown::Seconds(recharge.left),
+            })
+    }
+
+    /// Uses up a charge of a spell.
+    pub fn spend(&mut self, def: &SpellDef) {
+        let cooldown = match def.cooldown {
+            Some(cooldown) => cooldown,
+            None => return,
+        };
+
+        self.0
+            .entry(def.name.clone())
+            .or_insert(Recharge {
+                cooldown,
+                spent: 0,
+                left: length(cooldown),
+            })
+            .spent += 1;
+    }
+
+    fn tick(&mut self, turns: bool, elapsed: f32) {
+        for recharge in self.0.values_mut() {
+            if matches!(recharge.cooldown, Cooldown::Turns(_)) != turns {
+                continue;
+            }
+
+            recharge.left -= elapsed;
+            if recharge.left <= 0. {
+                recharge.spent -= 1;
+                recharge.left = length(recharge.cooldown);
+            }
+        }
+
+        self.0.retain(|_, recharge| recharge.spent > 0);
+    }
+}
+
+fn length(cooldown: Cooldown) -> f32 {
+    match cooldown {
+        Cooldown::Turns(turns) => turns as f32,
+        Cooldown::Seconds(seconds) => seconds,
+    }
+}
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct ManaTickLabel;
+
+pub struct ManaPlugin;
+
+impl Plugin for ManaPlugin {
+    fn build(&self, app: &mut App) {
+        // Rounds pass the same way as for status effects.
+        app.add_enter_system(TurnState::Player, tick_round.label(ManaTickLabel))
+            .add_system(
+                tick_round
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::FreeRoam)
+                    .run_if(world_ticked)
+                    .label(ManaTickLabel)
+                    .after(TurnLabel::Input),
+            )
+            .add_system(
+                tick_seconds
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap),
+            );
+    }
+}
+
+/// Regenerates mana and counts down cooldowns in turns.
+fn tick_round(
+    mut casters: Query<(
+        Option<(&mut Mana, &MaxMana, &ManaRegen)>,
+        Option<&mut SpellCharges>,
+    )>,
+) {
+    for (mana, charges) in casters.iter_mut() {
+        if let Some((mut mana, max, regen)) = mana {
+            **mana = (**mana + **regen).min(**max);
+        }
+
+        if let Some(mut charges) = charges {
+            charges.tick(true, 1.);
+        }
+    }
+}
+
+fn tick_seconds(time: Res<Time>, mut casters: Query<&mut SpellCharges>) {
+    for mut charges in casters.iter_mut() {
+        charges.tick(false, time.delta_seconds());
+    }
+}
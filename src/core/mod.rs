@@ -1,11 +1,46 @@
 //! Modules relating to user input and movement
+mod ai;
+mod allies;
+mod cards;
 mod cast_spell;
+mod combat;
+mod command;
 mod cursor;
+mod enemies;
+mod explore;
+mod hotbar;
+mod mana;
 mod mouse;
 mod movement;
+mod pickup;
+mod projectile;
+mod rest;
 mod spell;
+mod status;
+mod stealth;
+mod travel;
+mod turn;
+mod vfx;
 
+pub use ai::*;
+pub use allies::*;
+pub use cards::*;
+pub use cast_spell::*;
+pub use combat::*;
+pub use command::*;
 pub use cursor::*;
+pub use enemies::*;
+pub use explore::*;
+pub use hotbar::*;
+pub use mana::*;
+pub use mouse::*;
 pub use movement::*;
+pub use pickup::*;
+pub use projectile::*;
+pub use rest::*;
 pub use spell::*;
-pub use mouse::*;
+pub use status::*;
+pub use stealth::*;
+pub use travel::*;
+pub use turn::*;
+pub use vfx::*;
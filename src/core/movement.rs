@@ -1,15 +1,91 @@
+use std::time::Duration;
+
 use crate::{
-    components::PassiveTilePos,
-    util::{tile_from_trans, PlayerQuery},
+    components::{Enemy, Item, PassiveTilePos, Player},
+    map::{step_cost, MapTiles},
+    ui::AddConvar,
+    util::{offset_tile, tile_from_trans, trans_from_tile, PlayerQuery},
     ActiveState, GameState,
 };
 
 use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_console::ConsoleCommand;
+use bevy_easings::{Ease, EaseFunction, EasingComponent, EasingType};
+use bevy_ecs_tilemap::TilePos;
 use bevy_rapier2d::{plugin::RapierConfiguration, prelude::Velocity};
 use iyes_loopless::prelude::*;
 use leafwing_input_manager::{plugin::InputManagerPlugin, prelude::ActionState, Actionlike};
 use strum::EnumString;
 
+use super::{
+    Action, ActionEvent, ActionPerformed, Ally, CountPrefix, Repeat, RepeatUntil, TurnLabel,
+    TurnState,
+};
+
+/// How fast the player moves in free roam.
+pub const PLAYER_SPEED: f32 = 100.;
+
+/// How long it takes to animate a step from one tile to the next.
+const STEP_DURATION: Duration = Duration::from_millis(120);
+
+/// A direction on the tile grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+        Direction::UpLeft,
+        Direction::UpRight,
+        Direction::DownLeft,
+        Direction::DownRight,
+    ];
+
+    /// The change in tile position when stepping once in this direction.
+    pub fn offset(&self) -> IVec2 {
+        match self {
+            Direction::Up => IVec2::new(0, 1),
+            Direction::Down => IVec2::new(0, -1),
+            Direction::Left => IVec2::new(-1, 0),
+            Direction::Right => IVec2::new(1, 0),
+            Direction::UpLeft => IVec2::new(-1, 1),
+            Direction::UpRight => IVec2::new(1, 1),
+            Direction::DownLeft => IVec2::new(-1, -1),
+            Direction::DownRight => IVec2::new(1, -1),
+        }
+    }
+
+    /// The direction that steps by exactly this offset, if any.
+    pub fn from_offset(offset: IVec2) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|direction| direction.offset() == offset)
+    }
+
+    /// The horizontal and vertical parts of a diagonal direction, None if the direction is not diagonal.
+    pub fn split(&self) -> Option<(Direction, Direction)> {
+        match self {
+            Direction::UpLeft => Some((Direction::Left, Direction::Up)),
+            Direction::UpRight => Some((Direction::Right, Direction::Up)),
+            Direction::DownLeft => Some((Direction::Left, Direction::Down)),
+            Direction::DownRight => Some((Direction::Right, Direction::Down)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Actionlike, Debug, Component, Clone, Copy, EnumString)]
 #[strum(ascii_case_insensitive)]
 pub enum MovementAction {
@@ -17,32 +93,148 @@ pub enum MovementAction {
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl MovementAction {
+    pub const ALL: [MovementAction; 8] = [
+        MovementAction::Up,
+        MovementAction::Down,
+        MovementAction::Left,
+        MovementAction::Right,
+        MovementAction::UpLeft,
+        MovementAction::UpRight,
+        MovementAction::DownLeft,
+        MovementAction::DownRight,
+    ];
+
+    /// The direction this action moves in.
+    pub fn direction(&self) -> Direction {
+        match self {
+            MovementAction::Up => Direction::Up,
+            MovementAction::Down => Direction::Down,
+            MovementAction::Left => Direction::Left,
+            MovementAction::Right => Direction::Right,
+            MovementAction::UpLeft => Direction::UpLeft,
+            MovementAction::UpRight => Direction::UpRight,
+            MovementAction::DownLeft => Direction::DownLeft,
+            MovementAction::DownRight => Direction::DownRight,
+        }
+    }
+}
+
+/// Whether diagonal moves may squeeze between two walls.
+/// Only grid moves and paths go by this, in free roam the wall colliders decide what the player fits through.
+#[derive(ConsoleCommand)]
+#[console_command(name = "cut_corners")]
+pub struct CutCornersConvar {
+    /// Allow moving diagonally between two walls
+    pub on: bool,
+}
+
+/// Where a step from a tile in a direction ends up, if the terrain allows it. Other actors are not considered.
+pub fn step_target(
+    tiles: &mut MapTiles,
+    from: TilePos,
+    direction: Direction,
+    cut_corners: bool,
+) -> Option<TilePos> {
+    let target = offset_tile(&from, direction.offset())?;
+
+    if !tiles.is_walkable(target)
+        || (!cut_corners && squeezes_between_walls(tiles, from, direction))
+    {
+        return None;
+    }
+
+    Some(target)
+}
+
+/// Cost function for pathfinding that walks wherever [`step_target`] allows.
+pub fn walk_cost(
+    tiles: &mut MapTiles,
+    from: TilePos,
+    direction: Direction,
+    cut_corners: bool,
+) -> Option<u32> {
+    step_target(tiles, from, direction, cut_corners).map(|_| step_cost(direction))
+}
+
+/// Like [`walk_cost`], but only through tiles the player has seen.
+pub fn explored_walk_cost(
+    tiles: &mut MapTiles,
+    from: TilePos,
+    direction: Direction,
+    cut_corners: bool,
+) -> Option<u32> {
+    let cost = walk_cost(tiles, from, direction, cut_corners)?;
+    let to = offset_tile(&from, direction.offset())?;
+
+    tiles.is_explored(to).then_some(cost)
+}
+
+/// Whether a diagonal step from a tile passes between two tiles that can't be walked on.
+pub fn squeezes_between_walls(tiles: &mut MapTiles, from: TilePos, direction: Direction) -> bool {
+    match direction.split() {
+        Some((horizontal, vertical)) => [horizontal, vertical].into_iter().all(|part| {
+            offset_tile(&from, part.offset()).map_or(true, |pos| !tiles.is_walkable(pos))
+        }),
+        None => false,
+    }
 }
 
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct PlayerVelocityLabel;
+
 /// Handles all movement
 pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(InputManagerPlugin::<MovementAction>::default())
+            .add_convar(CutCornersConvar { on: false })
             // .add_system_to_stage(GameStage::Input, update_player_velocity)
             // .add_system(update_tilepos_transforms)
             .add_system(
                 update_player_velocity
                     .run_in_state(ActiveState::Playing)
-                    .run_in_state(GameState::FreeRoam),
+                    .run_in_state(GameState::FreeRoam)
+                    .label(PlayerVelocityLabel),
+            )
+            .add_system(
+                player_turn_input
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::TurnBased)
+                    .run_in_state(TurnState::Player)
+                    .label(TurnLabel::Input),
             )
+            .add_system(
+                resolve_moves
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(TurnLabel::Resolve)
+                    .after(TurnLabel::Input),
+            )
+            .add_enter_system(GameState::TurnBased, snap_to_grid)
             // .add_enter_system(PauseState::Paused, set_vel_zero)
             .add_enter_system(ActiveState::Paused, pause_physics)
+            .add_enter_system(ActiveState::GameOver, pause_physics)
             .add_exit_system(ActiveState::Paused, resume_physics)
             // )
             // .add_system(move_ent_with_transform.after(update_player_velocity))
             //TODO after
-            .add_system(update_player_tilepos);
+            // In turn based play the tile position is only changed when a move is committed.
+            .add_system(update_player_tilepos.run_in_state(GameState::FreeRoam));
     }
 }
 
-fn update_player_tilepos(mut query: Query<(&Transform, &mut PassiveTilePos), Changed<Transform>>) {
+/// Other actors move tile by tile even in free roam, their tile position is the one they committed to.
+fn update_player_tilepos(
+    mut query: Query<(&Transform, &mut PassiveTilePos), (With<Player>, Changed<Transform>)>,
+) {
     for (transform, mut pos) in query.iter_mut() {
         **pos = tile_from_trans(&transform.translation.xy());
     }
@@ -55,33 +247,178 @@ fn update_player_velocity(
 ) {
     let (mut vel, action_state) = player_query.single_mut();
 
-    //TODO: make this a constant or component
-    let speed = 100.;
-
     let mut input = Vec2::ZERO;
 
-    if action_state.pressed(MovementAction::Up) {
-        // input.y += speed * TILE_SIZE * time.delta_seconds();
-        input.y += 1.;
+    for action in MovementAction::ALL {
+        if !action_state.pressed(action) {
+            continue;
+        }
+
+        let offset = action.direction().offset();
+        input += Vec2::new(offset.x as f32, offset.y as f32);
     }
-    if action_state.pressed(MovementAction::Left) {
-        // input.x -= speed * TILE_SIZE * time.delta_seconds();
-        input.x -= 1.;
+
+    if input.length() != 0. {
+        input /= input.length();
     }
-    if action_state.pressed(MovementAction::Down) {
-        // input.y -= speed * TILE_SIZE * time.delta_seconds();
-        input.y -= 1.;
+
+    vel.linvel = input * PLAYER_SPEED;
+}
+
+/// Lines every actor up with the tile it is standing on, so that grid movement starts from the center of a tile.
+fn snap_to_grid(mut actors: Query<(&PassiveTilePos, &mut Transform, Option<&mut Velocity>)>) {
+    for (pos, mut transform, velocity) in actors.iter_mut() {
+        transform.translation = trans_from_tile(pos).extend(transform.translation.z);
+
+        if let Some(mut velocity) = velocity {
+            velocity.linvel = Vec2::ZERO;
+        }
     }
-    if action_state.pressed(MovementAction::Right) {
-        // input.x += speed * TILE_SIZE * time.delta_seconds();
-        input.x += 1.;
+}
+
+/// Turns movement keys into actions. Moving into an enemy attacks it instead.
+#[allow(clippy::type_complexity)]
+fn player_turn_input(
+    mut commands: Commands,
+    player_query: Query<
+        (
+            Entity,
+            &PassiveTilePos,
+            &ActionState<MovementAction>,
+            Option<&CountPrefix>,
+        ),
+        (
+            With<Player>,
+            Without<EasingComponent<Transform>>,
+            Without<Repeat>,
+        ),
+    >,
+    enemies: Query<(Entity, &PassiveTilePos), With<Enemy>>,
+    mut actions: EventWriter<ActionEvent>,
+) {
+    if let Ok((player, pos, action_state, count)) = player_query.get_single() {
+        let pressed = MovementAction::ALL
+            .into_iter()
+            .find(|action| action_state.pressed(*action));
+
+        if let Some(action) = pressed {
+            let direction = action.direction();
+            let target = offset_tile(pos, direction.offset());
+
+            if let Some((enemy, _)) = enemies
+                .iter()
+                .find(|(_, enemy_pos)| Some(***enemy_pos) == target)
+            {
+                // A count is meant for walking, it is dropped rather than repeating the attack.
+                commands.entity(player).remove::<CountPrefix>();
+                actions.send(ActionEvent {
+                    actor: player,
+                    action: Action::Attack(enemy),
+                });
+                return;
+            }
+
+            let action = Action::Move(direction);
+
+            match count {
+                Some(count) => {
+                    commands
+                        .entity(player)
+                        .remove::<CountPrefix>()
+                        .insert(Repeat::new(action, RepeatUntil::Count(count.times())));
+                }
+                None => actions.send(ActionEvent {
+                    actor: player,
+                    action,
+                }),
+            }
+        }
     }
+}
 
-    if input.length() != 0. {
-        input /= input.length();
+/// Moves actors one tile, unless the tile is blocked by a wall, a closed door or another actor,
+/// or the move is a diagonal squeezing between two walls and [`CutCornersConvar`] is off.
+/// The player trades places with an [`Ally`] in the way instead of being blocked by it.
+/// Bumping into something does not use up the turn.
+#[allow(clippy::type_complexity)]
+fn resolve_moves(
+    mut commands: Commands,
+    mut actions: EventReader<ActionEvent>,
+    mut performed: EventWriter<ActionPerformed>,
+    mut tiles: MapTiles,
+    mut actors: Query<
+        (
+            Entity,
+            &mut PassiveTilePos,
+            &Transform,
+            Option<&Player>,
+            Option<&Ally>,
+        ),
+        Without<Item>,
+    >,
+    cut_corners: Res<CutCornersConvar>,
+) {
+    for event in actions.iter() {
+        let direction = match event.action {
+            Action::Move(direction) => direction,
+            _ => continue,
+        };
+
+        let (from, transform, is_player) = match actors.get(event.actor) {
+            Ok((_, pos, transform, player, _)) => (**pos, *transform, player.is_some()),
+            Err(_) => continue,
+        };
+
+        let target = match step_target(&mut tiles, from, direction, cut_corners.on) {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let occupant = actors
+            .iter()
+            .find(|(_, pos, _, _, _)| **pos == target)
+            .map(|(entity, _, transform, _, ally)| (entity, *transform, ally.is_some()));
+
+        let swap = match occupant {
+            None => None,
+            Some((ally, ally_transform, true)) if is_player => Some((ally, ally_transform)),
+            Some(_) => continue,
+        };
+
+        // Commit the move, the animation is only cosmetic.
+        let (_, mut pos, _, _, _) = actors.get_mut(event.actor).unwrap();
+        **pos = target;
+        commands
+            .entity(event.actor)
+            .insert(step_easing(transform, target));
+
+        if let Some((ally, ally_transform)) = swap {
+            let (_, mut pos, _, _, _) = actors.get_mut(ally).unwrap();
+            **pos = from;
+            commands
+                .entity(ally)
+                .insert(step_easing(ally_transform, from));
+        }
+
+        performed.send(ActionPerformed {
+            actor: event.actor,
+            action: event.action,
+        });
     }
+}
 
-    vel.linvel = input * speed;
+/// Eases a transform over to a tile, taking as long as a step.
+fn step_easing(transform: Transform, to: TilePos) -> EasingComponent<Transform> {
+    transform.ease_to(
+        Transform {
+            translation: trans_from_tile(&to).extend(transform.translation.z),
+            ..transform
+        },
+        EaseFunction::QuadraticInOut,
+        EasingType::Once {
+            duration: STEP_DURATION,
+        },
+    )
 }
 
 fn pause_physics(mut config: ResMut<RapierConfiguration>) {
@@ -0,0 +1,80 @@
+//! Items lying on the map, and picking them up by walking over them.
+use bevy::prelude::*;
+use bevy_ecs_tilemap::TilePos;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    components::{Inventory, Item, PassiveTilePos, Player},
+    util::trans_from_tile,
+    ActiveState, GameState,
+};
+
+/// How big an item is drawn, items have no sprites of their own yet.
+const ITEM_SIZE: f32 = 6.;
+
+/// Puts an item on the map.
+#[derive(Debug, Clone)]
+pub struct SpawnItem {
+    pub name: String,
+    pub pos: TilePos,
+}
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnItem>()
+            .add_system(spawn_items.run_not_in_state(GameState::GeneratingMap))
+            .add_system(
+                pick_up_items
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap),
+            );
+    }
+}
+
+fn spawn_items(mut commands: Commands, mut requests: EventReader<SpawnItem>) {
+    for SpawnItem { name, pos } in requests.iter() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::GOLD,
+                    custom_size: Some(Vec2::splat(ITEM_SIZE)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(trans_from_tile(pos).extend(0.5)),
+                ..Default::default()
+            })
+            .insert(Item { name: name.clone() })
+            .insert(PassiveTilePos(*pos));
+
+        debug!("Spawned {name} at {pos:?}");
+    }
+}
+
+fn pick_up_items(
+    mut commands: Commands,
+    mut player_query: Query<
+        (&PassiveTilePos, &mut Inventory),
+        (With<Player>, Changed<PassiveTilePos>),
+    >,
+    mut items: Query<(Entity, &Item, &PassiveTilePos, Option<&mut Visibility>)>,
+) {
+    if let Ok((player_pos, mut inventory)) = player_query.get_single_mut() {
+        for (entity, item, pos, visibility) in items.iter_mut() {
+            if **pos != **player_pos {
+                continue;
+            }
+
+            info!("Picked up {}", item.name);
+
+            if let Some(mut visibility) = visibility {
+                visibility.is_visible = false;
+            }
+
+            // Carried items are no longer on the map.
+            commands.entity(entity).remove::<PassiveTilePos>();
+            inventory.push(entity);
+        }
+    }
+}
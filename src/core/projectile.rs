@@ -0,0 +1,323 @@
+//! Projectiles, flying in a straight line until they hit a wall or an actor.
+//! In turn based play a projectile hits the moment it is fired, and its sprite only shows the flight, as a [`Vfx`].
+//! In free roam it is a rapier body, and hits whatever it flies into.
+//! Either way it only hits the actors its [`Affects`] includes, and flies past the rest.
+use std::time::Duration;
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_easings::{Ease, EaseFunction, EasingType};
+use bevy_ecs_tilemap::TilePos;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+use rand::Rng;
+
+use crate::{
+    components::{Faction, Health, PassiveTilePos, Projectile, RangedAttack},
+    map::MapTiles,
+    util::{tile_from_trans, tile_ray, trans_from_tile, TILE_SIZE},
+    ActiveState, GameState,
+};
+
+use super::{
+    distance, Action, ActionEvent, ActionPerformed, Affects, ApplyStatus, DamageEvent, DamageLabel,
+    TurnLabel, Vfx,
+};
+
+/// How fast projectiles fly, in tiles per second.
+const PROJECTILE_SPEED: f32 = 16.;
+
+/// How big a projectile is drawn.
+const PROJECTILE_SIZE: f32 = 4.;
+
+/// Which actors the ranged attacks of actors hit, spells say for themselves.
+pub const RANGED_AFFECTS: Affects = Affects::Foes;
+
+/// Launches a projectile from a tile towards another.
+#[derive(Debug, Clone)]
+pub struct FireProjectile {
+    /// Who fired it, never hit by it.
+    pub source: Option<Entity>,
+    /// The side of whoever fired it.
+    pub faction: Faction,
+    /// Which actors it hits, the rest it flies past.
+    pub affects: Affects,
+    pub from: TilePos,
+    pub target: TilePos,
+    pub projectile: Projectile,
+}
+
+/// A projectile flying through free roam.
+#[derive(Debug, Component)]
+pub struct Flying {
+    source: Option<Entity>,
+    faction: Faction,
+    affects: Affects,
+    projectile: Projectile,
+    from: TilePos,
+    /// The tile it was last checked on.
+    last: TilePos,
+    /// Actors it has already passed through.
+    hit: Vec<Entity>,
+}
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct ProjectileLabel;
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FireProjectile>()
+            .add_system(
+                resolve_fire
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(TurnLabel::Resolve)
+                    .after(TurnLabel::Input),
+            )
+            .add_system(
+                launch_projectiles
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(ProjectileLabel)
+                    .after(TurnLabel::Resolve)
+                    .before(DamageLabel),
+            )
+            .add_system(
+                fly_projectiles
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::FreeRoam)
+                    .after(ProjectileLabel)
+                    .before(DamageLabel),
+            )
+            // Whatever is still in the air is done flying when turn based play starts, or the level is left.
+            .add_enter_system(GameState::TurnBased, remove_flying)
+            .add_enter_system(GameState::GeneratingMap, remove_flying);
+    }
+}
+
+/// Whether a projectile from one tile would reach another without hitting a wall or anything in between.
+/// `occupant` tells the faction of the actor standing on a tile, if any. Like the projectile itself,
+/// the shot only counts actors its `affects` includes as in the way.
+pub fn clear_shot(
+    tiles: &mut MapTiles,
+    from: TilePos,
+    to: TilePos,
+    faction: Faction,
+    affects: Affects,
+    occupant: impl Fn(TilePos) -> Option<Faction>,
+) -> bool {
+    tile_ray(from, to, distance(from, to))
+        .into_iter()
+        .all(|pos| {
+            pos == to
+                || (tiles.is_walkable(pos)
+                    && !occupant(pos)
+                        .map_or(false, |other| affects.includes(faction, false, other)))
+        })
+}
+
+fn hit(
+    source: Option<Entity>,
+    target: Entity,
+    projectile: &Projectile,
+    damage: &mut EventWriter<DamageEvent>,
+    statuses: &mut EventWriter<ApplyStatus>,
+) {
+    let (least, most) = projectile.damage;
+
+    damage.send(DamageEvent {
+        source,
+        target,
+        amount: rand::thread_rng().gen_range(least..=most.max(least)),
+        kind: projectile.kind,
+    });
+
+    if let Some(mut effect) = projectile.inflicts {
+        effect.source = source;
+        statuses.send(ApplyStatus { target, effect });
+    }
+}
+
+fn resolve_fire(
+    mut actions: EventReader<ActionEvent>,
+    mut performed: EventWriter<ActionPerformed>,
+    mut fire: EventWriter<FireProjectile>,
+    shooters: Query<(&PassiveTilePos, &RangedAttack, Option<&Faction>)>,
+) {
+    for event in actions.iter() {
+        let target = match event.action {
+            Action::Fire(target) => target,
+            _ => continue,
+        };
+
+        if let Ok((pos, ranged, faction)) = shooters.get(event.actor) {
+            fire.send(FireProjectile {
+                source: Some(event.actor),
+                faction: faction.copied().unwrap_or_default(),
+                affects: RANGED_AFFECTS,
+                from: **pos,
+                target,
+                projectile: ranged.0.clone(),
+            });
+
+            performed.send(ActionPerformed {
+                actor: event.actor,
+                action: event.action,
+            });
+        }
+    }
+}
+
+fn launch_projectiles(
+    mut commands: Commands,
+    mut events: EventReader<FireProjectile>,
+    mut tiles: MapTiles,
+    actors: Query<(Entity, &PassiveTilePos, Option<&Faction>), With<Health>>,
+    game_state: Res<CurrentState<GameState>>,
+    mut damage: EventWriter<DamageEvent>,
+    mut statuses: EventWriter<ApplyStatus>,
+) {
+    for FireProjectile {
+        source,
+        faction,
+        affects,
+        from,
+        target,
+        projectile,
+    } in events.iter()
+    {
+        let start = trans_from_tile(from);
+        let sprite = SpriteBundle {
+            sprite: Sprite {
+                color: projectile.color,
+                custom_size: Some(Vec2::splat(PROJECTILE_SIZE)),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(start.extend(2.)),
+            ..Default::default()
+        };
+
+        if game_state.0 == GameState::FreeRoam {
+            let direction = (trans_from_tile(target) - start).normalize_or_zero();
+
+            commands
+                .spawn_bundle(sprite)
+                .insert(RigidBody::Dynamic)
+                .insert(Sensor(true))
+                .insert(Collider::ball(PROJECTILE_SIZE / 2.))
+                .insert(GravityScale(0.))
+                .insert(Velocity::linear(direction * PROJECTILE_SPEED * TILE_SIZE))
+                .insert(Flying {
+                    source: *source,
+                    faction: *faction,
+                    affects: *affects,
+                    projectile: projectile.clone(),
+                    from: *from,
+                    last: *from,
+                    hit: vec![],
+                });
+            continue;
+        }
+
+        // Walk the path right away, stopping at the first wall or at the last actor it can't pierce.
+        let mut end = *from;
+        let mut pierce = projectile.pierce;
+
+        for pos in tile_ray(*from, *target, projectile.range) {
+            if !tiles.is_walkable(pos) {
+                break;
+            }
+            end = pos;
+
+            let struck = actors.iter().find(|(entity, actor_pos, other)| {
+                ***actor_pos == pos
+                    && Some(*entity) != *source
+                    && affects.includes(*faction, false, other.copied().unwrap_or_default())
+            });
+
+            if let Some((entity, _, _)) = struck {
+                hit(*source, entity, projectile, &mut damage, &mut statuses);
+
+                if pierce == 0 {
+                    break;
+                }
+                pierce -= 1;
+            }
+        }
+
+        let flight = Duration::from_secs_f32(distance(*from, end) as f32 / PROJECTILE_SPEED);
+        let transform = sprite.transform;
+
+        commands
+            .spawn_bundle(sprite)
+            .insert(Vfx)
+            .insert(transform.ease_to(
+                Transform {
+                    translation: trans_from_tile(&end).extend(transform.translation.z),
+                    ..transform
+                },
+                EaseFunction::QuadraticIn,
+                EasingType::Once { duration: flight },
+            ));
+    }
+}
+
+/// Checks every tile a free roam projectile enters for walls and actors.
+fn fly_projectiles(
+    mut commands: Commands,
+    mut projectiles: Query<(Entity, &Transform, &mut Flying)>,
+    mut tiles: MapTiles,
+    actors: Query<(Entity, &PassiveTilePos, Option<&Faction>), With<Health>>,
+    mut damage: EventWriter<DamageEvent>,
+    mut statuses: EventWriter<ApplyStatus>,
+) {
+    for (entity, transform, mut flying) in projectiles.iter_mut() {
+        let pos = tile_from_trans(&transform.translation.xy());
+        if pos == flying.last {
+            continue;
+        }
+        flying.last = pos;
+
+        if !tiles.is_walkable(pos) || distance(flying.from, pos) > flying.projectile.range {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let struck = actors
+            .iter()
+            .find(|(actor, actor_pos, other)| {
+                ***actor_pos == pos
+                    && Some(*actor) != flying.source
+                    && !flying.hit.contains(actor)
+                    && flying.affects.includes(
+                        flying.faction,
+                        false,
+                        other.copied().unwrap_or_default(),
+                    )
+            })
+            .map(|(actor, _, _)| actor);
+
+        if let Some(actor) = struck {
+            hit(
+                flying.source,
+                actor,
+                &flying.projectile,
+                &mut damage,
+                &mut statuses,
+            );
+
+            if flying.hit.len() as u32 >= flying.projectile.pierce {
+                commands.entity(entity).despawn();
+            } else {
+                flying.hit.push(actor);
+            }
+        }
+    }
+}
+
+fn remove_flying(mut commands: Commands, flying: Query<Entity, With<Flying>>) {
+    for entity in flying.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,371 @@
+//! Waiting, resting and repeating an action a number of times.
+//! All of these are turn based, using one from free roam switches to turn based play until it is done.
+//! Typing a number first repeats the next wait, search or move that many times, `5` then `x` searches five times.
+use bevy::prelude::*;
+use bevy_easings::EasingComponent;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::{
+    prelude::{ActionState, InputMap},
+    Actionlike,
+};
+
+use crate::{
+    components::{Enemy, Health, Item, MaxHealth, NaturalRegen, PassiveTilePos, Player},
+    map::{FieldOfView, MapTiles},
+    ActiveState, GameState,
+};
+
+use super::{
+    step_target, visible_hostiles, Action, ActionEvent, ActionPerformed, Ally, AutoExplore,
+    CommandAction, CutCornersConvar, HealEvent, HotbarAction, MovementAction, StopReason, Travel,
+    TurnLabel, TurnState,
+};
+
+const DIGITS: [KeyCode; 10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// How many times to repeat the next action. Typed in with the number keys that aren't bound to anything else.
+#[derive(Debug, Component, Deref, DerefMut)]
+pub struct CountPrefix(pub u32);
+
+impl CountPrefix {
+    /// How many times the action is taken, a count of 0 still takes it once.
+    pub fn times(&self) -> u32 {
+        self.0.max(1)
+    }
+}
+
+/// The player went turn based for a repeated action, and goes back to free roam once it is done.
+#[derive(Debug, Component)]
+pub struct ResumeFreeRoam;
+
+/// When a repeated action is done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatUntil {
+    /// After this many more turns.
+    Count(u32),
+    /// Once health is full.
+    Healed,
+}
+
+/// The player performs an action every turn until it is done or something interrupts it.
+#[derive(Debug, Component)]
+pub struct Repeat {
+    action: Action,
+    until: RepeatUntil,
+    /// Health last turn, losing any interrupts. Filled in on the first turn.
+    health: Option<i32>,
+    /// Hostiles that were already in view on the first turn, these don't interrupt.
+    known_hostiles: Option<Vec<Entity>>,
+}
+
+impl Repeat {
+    pub fn new(action: Action, until: RepeatUntil) -> Self {
+        Self {
+            action,
+            until,
+            health: None,
+            known_hostiles: None,
+        }
+    }
+
+    /// What the player is doing, for messages.
+    fn activity(&self) -> &'static str {
+        match (self.action, self.until) {
+            (Action::Wait, RepeatUntil::Healed) => "resting",
+            (Action::Wait, _) => "waiting",
+            (Action::Search, _) => "searching",
+            (Action::Move(_), _) => "walking",
+            (Action::Attack(_), _) => "attacking",
+            (Action::Fire(_), _) => "shooting",
+            (Action::Cast, _) => "casting",
+        }
+    }
+}
+
+pub struct RestPlugin;
+
+impl Plugin for RestPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            count_input
+                .run_in_state(ActiveState::Playing)
+                .run_not_in_state(GameState::GeneratingMap),
+        )
+        .add_system(
+            command_input
+                .run_in_state(ActiveState::Playing)
+                .run_not_in_state(GameState::GeneratingMap),
+        )
+        .add_system(
+            repeat_actions
+                .run_in_state(ActiveState::Playing)
+                .run_in_state(GameState::TurnBased)
+                .run_in_state(TurnState::Player)
+                .label(TurnLabel::Input),
+        )
+        .add_system(
+            resume_free_roam
+                .run_in_state(ActiveState::Playing)
+                .run_in_state(GameState::TurnBased)
+                .run_in_state(TurnState::Player),
+        )
+        .add_system(
+            natural_regen
+                .run_in_state(ActiveState::Playing)
+                .run_in_state(GameState::TurnBased)
+                .after(TurnLabel::Resolve),
+        );
+    }
+}
+
+/// Whether a key on its own is bound to an action.
+fn is_bound<A: Actionlike>(input: &InputMap<A>, key: KeyCode) -> bool {
+    input.iter().any(|(inputs, _)| inputs.contains(&key.into()))
+}
+
+/// Number keys start or add to the count, unless they were bound to something else.
+#[allow(clippy::type_complexity)]
+fn count_input(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut player_query: Query<
+        (
+            Entity,
+            Option<&mut CountPrefix>,
+            &InputMap<MovementAction>,
+            &InputMap<CommandAction>,
+            &InputMap<HotbarAction>,
+            &ActionState<HotbarAction>,
+        ),
+        With<Player>,
+    >,
+) {
+    let (entity, mut count, movement, command, hotbar, hotbar_state) =
+        match player_query.get_single_mut() {
+            Ok(player) => player,
+            Err(_) => return,
+        };
+
+    // The hotbar is on the number keys while shift is held.
+    if HotbarAction::ALL
+        .into_iter()
+        .any(|action| hotbar_state.pressed(action))
+    {
+        return;
+    }
+
+    for (digit, key) in DIGITS.into_iter().enumerate() {
+        if !keys.just_pressed(key)
+            || is_bound(movement, key)
+            || is_bound(command, key)
+            || is_bound(hotbar, key)
+        {
+            continue;
+        }
+
+        match count.as_deref_mut() {
+            Some(count) => **count = count.saturating_mul(10).saturating_add(digit as u32),
+            None => {
+                commands.entity(entity).insert(CountPrefix(digit as u32));
+                return;
+            }
+        }
+    }
+}
+
+fn command_input(
+    mut commands: Commands,
+    player_query: Query<
+        (
+            Entity,
+            &ActionState<CommandAction>,
+            &Health,
+            &MaxHealth,
+            &FieldOfView,
+            Option<&CountPrefix>,
+        ),
+        With<Player>,
+    >,
+    hostiles: Query<(Entity, &PassiveTilePos), With<Enemy>>,
+    state: Res<CurrentState<GameState>>,
+) {
+    let (entity, action_state, health, max_health, fov, count) = match player_query.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    let times = count.map_or(1, CountPrefix::times);
+
+    let repeat = if action_state.just_pressed(CommandAction::Wait) {
+        Repeat::new(Action::Wait, RepeatUntil::Count(times))
+    } else if action_state.just_pressed(CommandAction::Search) {
+        Repeat::new(Action::Search, RepeatUntil::Count(times))
+    } else if action_state.just_pressed(CommandAction::Rest) {
+        if **health >= **max_health {
+            info!("Already at full health");
+            return;
+        }
+
+        if !visible_hostiles(fov, &hostiles).is_empty() {
+            info!("Can't rest with hostiles in view");
+            return;
+        }
+
+        Repeat::new(Action::Wait, RepeatUntil::Healed)
+    } else {
+        return;
+    };
+
+    commands
+        .entity(entity)
+        .remove::<CountPrefix>()
+        .remove::<Travel>()
+        .remove::<AutoExplore>()
+        .insert(repeat);
+
+    if state.0 == GameState::FreeRoam {
+        commands.entity(entity).insert(ResumeFreeRoam);
+        commands.insert_resource(NextState(GameState::TurnBased));
+    }
+}
+
+/// Goes back to free roam once the repeated action that left it is over, unless it was stopped by a hostile.
+#[allow(clippy::type_complexity)]
+fn resume_free_roam(
+    mut commands: Commands,
+    player_query: Query<
+        (Entity, &FieldOfView),
+        (
+            With<Player>,
+            With<ResumeFreeRoam>,
+            Without<Repeat>,
+            Without<EasingComponent<Transform>>,
+        ),
+    >,
+    hostiles: Query<(Entity, &PassiveTilePos), With<Enemy>>,
+) {
+    if let Ok((entity, fov)) = player_query.get_single() {
+        commands.entity(entity).remove::<ResumeFreeRoam>();
+
+        if visible_hostiles(fov, &hostiles).is_empty() {
+            commands.insert_resource(NextState(GameState::FreeRoam));
+        }
+    }
+}
+
+/// Sends the repeated action once per turn, unless it is done or interrupted.
+fn repeat_actions(
+    mut commands: Commands,
+    mut player_query: Query<
+        (
+            Entity,
+            &PassiveTilePos,
+            &FieldOfView,
+            &Health,
+            &MaxHealth,
+            &mut Repeat,
+            &ActionState<MovementAction>,
+        ),
+        (With<Player>, Without<EasingComponent<Transform>>),
+    >,
+    hostiles: Query<(Entity, &PassiveTilePos), With<Enemy>>,
+    // Allies trade places with the player instead of blocking the way.
+    actors: Query<(Entity, &PassiveTilePos), (Without<Item>, Without<Ally>)>,
+    mut tiles: MapTiles,
+    cut_corners: Res<CutCornersConvar>,
+    mut actions: EventWriter<ActionEvent>,
+) {
+    let (entity, pos, fov, health, max_health, mut repeat, action_state) =
+        match player_query.get_single_mut() {
+            Ok(player) => player,
+            Err(_) => return,
+        };
+
+    let visible = visible_hostiles(fov, &hostiles);
+    let known_hostiles = repeat.known_hostiles.get_or_insert_with(|| visible.clone());
+    let new_hostile = visible
+        .iter()
+        .any(|hostile| !known_hostiles.contains(hostile));
+
+    let blocked = match repeat.action {
+        Action::Move(direction) => {
+            match step_target(&mut tiles, **pos, direction, cut_corners.on) {
+                Some(target) => actors
+                    .iter()
+                    .any(|(other, other_pos)| other != entity && **other_pos == target),
+                None => true,
+            }
+        }
+        _ => false,
+    };
+
+    let interruption = if MovementAction::ALL
+        .into_iter()
+        .any(|action| action_state.just_pressed(action))
+    {
+        Some(StopReason::Cancelled)
+    } else if repeat.health.map_or(false, |last| **health < last) {
+        Some(StopReason::TookDamage)
+    } else if new_hostile {
+        Some(StopReason::HostileInView)
+    } else if repeat.until == RepeatUntil::Healed && **health >= **max_health {
+        Some(StopReason::Healed)
+    } else if blocked {
+        Some(StopReason::Blocked)
+    } else {
+        None
+    };
+
+    if let Some(reason) = interruption {
+        info!("Stopped {}: {reason}", repeat.activity());
+        commands.entity(entity).remove::<Repeat>();
+        return;
+    }
+
+    if let RepeatUntil::Count(remaining) = &mut repeat.until {
+        if *remaining == 0 {
+            commands.entity(entity).remove::<Repeat>();
+            return;
+        }
+
+        *remaining -= 1;
+    }
+
+    repeat.health = Some(**health);
+    actions.send(ActionEvent {
+        actor: entity,
+        action: repeat.action,
+    });
+}
+
+fn natural_regen(
+    mut performed: EventReader<ActionPerformed>,
+    mut actors: Query<&mut NaturalRegen>,
+    mut heals: EventWriter<HealEvent>,
+) {
+    for action in performed.iter() {
+        if let Ok(mut regen) = actors.get_mut(action.actor) {
+            regen.turns += 1;
+
+            if regen.turns >= regen.every {
+                regen.turns = 0;
+                heals.send(HealEvent {
+                    source: None,
+                    target: action.actor,
+                    amount: 1,
+                });
+            }
+        }
+    }
+}
@@ -1,78 +1,719 @@
-use crate::components::Health;
-use bevy::ecs::event::Events;
-use bevy::ecs::world::EntityMut;
-use bevy::prelude::*;
+//! Spells, defined in `assets/spells/*.spell.ron`.
+//! A spell is a list of effects, applied to every actor in its area or once at the tile it is cast at.
+use bevy::{prelude::*, reflect::TypeUuid};
+use bevy_easings::{Ease, EaseFunction, EasingType};
 use bevy_ecs_tilemap::TilePos;
+use iyes_loopless::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
 
-// use super::cast_spell::SpellCast;
-use super::cast_spell::{self, SpellCast};
-use super::TileCursor;
+use crate::{
+    components::{DamageType, Faction, Health, Mana, PassiveTilePos, Projectile, StatusEffect},
+    map::{AlterTerrain, MapTiles, Terrain},
+    util::{offset_tile, tile_ray, trans_from_tile, AssetFolder, NamedAsset, RonAsset, RonLoader},
+    ActiveState, GameState,
+};
 
-#[derive(Debug, Clone, Copy)]
-pub enum Spell {
-    Fireball,
-    Heal,
+use super::cast_spell::{self, CastRefused, SpellCast};
+use super::{
+    distance, player_can_act, ApplyStatus, Cooldown, DamageEvent, Direction, FireProjectile,
+    HealEvent, SpawnEnemy, SpellCharges, TileCursor, TurnLabel,
+};
+
+/// How long it takes to animate a push.
+const PUSH_DURATION: std::time::Duration = std::time::Duration::from_millis(120);
+
+/// Widest angle between the aim of a cone and a tile inside it.
+const CONE_ANGLE: f32 = std::f32::consts::FRAC_PI_4;
+
+/// The tiles a spell affects, around the tile it is cast at or reaching out from the caster.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum Area {
+    /// Only the tile itself.
+    Tile,
+    /// Every tile within a radius.
+    Circle(u32),
+    /// Every tile within a number of steps, diagonals included.
+    Square(u32),
+    /// A beam this long from the caster, through the tile.
+    Line(u32),
+    /// A cone this long from the caster, widening towards the tile.
+    Cone(u32),
+    /// The edge of a circle.
+    Ring(u32),
+    /// The actor at the tile, then jumping to the nearest actor within `range` of the last one, up to `jumps` times.
+    Chain { jumps: u32, range: u32 },
 }
 
-impl Spell {
-    fn take_action(&self, mut entity: EntityMut) {
-        match self {
-            Spell::Fireball => {
-                if let Some(mut hp) = entity.get_mut::<Health>() {
-                    hp.0 -= 10;
+impl Area {
+    /// The tiles affected by a spell cast from `caster` at `target`.
+    /// `actors` are where actors other than the caster stand, for chains to jump between.
+    /// Walls shelter whatever is behind them, from the caster for lines and cones, and from the target otherwise.
+    pub fn tiles(
+        &self,
+        caster: TilePos,
+        target: TilePos,
+        actors: &[TilePos],
+        map: &mut MapTiles,
+    ) -> Vec<TilePos> {
+        let origin = match self {
+            Area::Line(_) | Area::Cone(_) => caster,
+            _ => target,
+        };
+
+        self.shape(caster, target, actors, |from, to| map.in_sight(from, to))
+            .into_iter()
+            .filter(|pos| map.in_sight(origin, *pos))
+            .collect()
+    }
+
+    /// The tiles of the area before walls are taken into account, other than for chains jumping.
+    fn shape(
+        &self,
+        caster: TilePos,
+        target: TilePos,
+        actors: &[TilePos],
+        mut in_sight: impl FnMut(TilePos, TilePos) -> bool,
+    ) -> Vec<TilePos> {
+        match *self {
+            Area::Tile => vec![target],
+            Area::Circle(radius) => TileCursor::draw_circle(&target, radius),
+            Area::Square(size) => square(target, size),
+            Area::Line(length) => tile_ray(caster, target, length),
+            Area::Cone(length) => {
+                let aim = tile_offset(caster, target).as_vec2();
+                if aim == Vec2::ZERO {
+                    return vec![];
                 }
+
+                square(caster, length)
+                    .into_iter()
+                    .filter(|pos| {
+                        let offset = tile_offset(caster, *pos).as_vec2();
+                        offset != Vec2::ZERO && aim.angle_between(offset).abs() <= CONE_ANGLE
+                    })
+                    .collect()
             }
-            Spell::Heal => {
-                if let Some(mut hp) = entity.get_mut::<Health>() {
-                    hp.0 += 10;
+            Area::Ring(radius) => {
+                let inside = match radius {
+                    0 => vec![],
+                    _ => TileCursor::draw_circle(&target, radius - 1),
+                };
+
+                TileCursor::draw_circle(&target, radius)
+                    .into_iter()
+                    .filter(|pos| !inside.contains(pos))
+                    .collect()
+            }
+            // There is nothing for a chain to start from without an actor at the tile.
+            Area::Chain { .. } if !actors.contains(&target) => vec![],
+            Area::Chain { jumps, range } => {
+                let mut tiles = vec![target];
+                let mut last = target;
+
+                for _ in 0..jumps {
+                    let next = actors
+                        .iter()
+                        .filter(|pos| {
+                            !tiles.contains(pos)
+                                && distance(last, **pos) <= range
+                                && in_sight(last, **pos)
+                        })
+                        .min_by_key(|pos| distance(last, **pos));
+
+                    match next {
+                        Some(next) => {
+                            tiles.push(*next);
+                            last = *next;
+                        }
+                        None => break,
+                    }
                 }
+
+                tiles
             }
         }
     }
 }
 
-fn recieved_spell(world: &mut World) {
-    let entities = world
-        .query::<(Entity, &TilePos)>()
-        .iter(&world)
-        .map(|(e, p)| (e, *p))
-        .collect::<Vec<(Entity, TilePos)>>();
+/// Every tile within a number of steps of a tile, diagonals included.
+fn square(center: TilePos, size: u32) -> Vec<TilePos> {
+    let mut tiles = vec![];
+    for x in center.0.saturating_sub(size)..=center.0.saturating_add(size) {
+        for y in center.1.saturating_sub(size)..=center.1.saturating_add(size) {
+            tiles.push(TilePos(x, y));
+        }
+    }
+    tiles
+}
 
-    let spells_sent = world.get_resource::<Events<SpellCast>>().unwrap();
+/// How far over and up one tile is from another.
+fn tile_offset(from: TilePos, to: TilePos) -> IVec2 {
+    IVec2::new(to.0 as i32 - from.0 as i32, to.1 as i32 - from.1 as i32)
+}
 
-    let casts = spells_sent
-        .get_reader()
-        .iter(&spells_sent)
-        .map(|s| *s)
-        .collect::<Vec<_>>();
+/// Which actors in its area a spell affects, by how they side with the caster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum Affects {
+    /// Anyone not allied with the caster.
+    #[default]
+    Foes,
+    /// The caster and its allies.
+    Allies,
+    /// Anyone, the caster included.
+    Everyone,
+}
 
-    for spell in casts {
-        // TODO: Same r everywhere
-        let circle = TileCursor::draw_circle(&spell.position, 2)
-            .into_iter()
-            .collect::<Vec<_>>();
+impl Affects {
+    pub fn includes(&self, caster: Faction, is_caster: bool, other: Faction) -> bool {
+        let allied = is_caster || caster.allied_with(other);
 
-        if true {
-            let mut entities_effected = vec![];
-            for (entity, pos) in entities.iter() {
-                if circle.iter().any(|t| *t == *pos) {
-                    entities_effected.push(*entity);
-                }
-            }
+        match self {
+            Affects::Foes => !allied,
+            Affects::Allies => allied,
+            Affects::Everyone => true,
+        }
+    }
+}
 
-            for entity in entities_effected {
-                spell.spell.take_action(world.entity_mut(entity));
+/// Something a spell does.
+#[derive(Debug, Clone, Deserialize)]
+pub enum SpellEffect {
+    /// Damages every actor in the area, by an amount between the least and most.
+    Damage {
+        amount: (i32, i32),
+        #[serde(default)]
+        kind: DamageType,
+    },
+    /// Heals every actor in the area.
+    Heal(i32),
+    /// Applies a status effect to every actor in the area.
+    Status(StatusEffect),
+    /// Pushes every actor in the area this many tiles away from its center.
+    Push(u32),
+    /// Spawns enemies by name in the area, as allies when the player casts it.
+    Summon { name: String, count: u32 },
+    /// Moves the caster to the tile the spell is cast at.
+    Teleport,
+    /// Fires a projectile from the caster at the tile, which only hits the actors the spell affects.
+    Projectile(Projectile),
+    /// Turns every tile of one terrain in the area into another, for some turns if given.
+    /// Nothing is raised under an actor.
+    Reshape {
+        from: Terrain,
+        to: Terrain,
+        #[serde(default)]
+        turns: Option<u32>,
+    },
+}
+
+/// Everything about a spell.
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "0d6c2a8e-93a1-4f57-b6e2-5c1f4e7a2d90"]
+pub struct SpellDef {
+    /// What the spell is found by.
+    pub name: String,
+    /// Path of the icon, relative to the assets folder.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Color of the burst drawn over its area when cast, nothing is drawn if none.
+    #[serde(default)]
+    pub color: Option<Color>,
+    pub area: Area,
+    #[serde(default)]
+    pub affects: Affects,
+    /// How far from the caster it can be cast.
+    pub range: u32,
+    /// Mana it takes to cast.
+    #[serde(default)]
+    pub cost: u32,
+    /// How long a spent charge takes to come back, none if it can be cast at will.
+    #[serde(default)]
+    pub cooldown: Option<Cooldown>,
+    /// How many times it can be cast in a row before waiting on the cooldown.
+    #[serde(default = "one")]
+    pub charges: u32,
+    pub effects: Vec<SpellEffect>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+impl SpellDef {
+    /// Whether the caster can aim the spell at a tile.
+    pub fn check_target(
+        &self,
+        caster: TilePos,
+        target: TilePos,
+        map: &mut MapTiles,
+    ) -> Result<(), CastRefusal> {
+        if distance(caster, target) > self.range {
+            Err(CastRefusal::OutOfRange)
+        } else if !map.in_sight(caster, target) {
+            Err(CastRefusal::OutOfSight)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether the caster has the mana and a charge to cast the spell.
+    pub fn check_resources(
+        &self,
+        mana: Option<&Mana>,
+        charges: Option<&SpellCharges>,
+    ) -> Result<(), CastRefusal> {
+        if self.cost > 0 && mana.map_or(true, |mana| **mana < self.cost as i32) {
+            return Err(CastRefusal::NotEnoughMana);
+        }
+
+        match charges {
+            Some(charges) if charges.available(self) == 0 => Err(CastRefusal::Recharging(
+                charges.ready_in(self).unwrap_or(Cooldown::Turns(0)),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Why a spell can't be cast.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CastRefusal {
+    OutOfRange,
+    OutOfSight,
+    NotEnoughMana,
+    /// There is no card for it in the hand.
+    NotInHand,
+    /// Every charge is spent, the next is back after this long.
+    Recharging(Cooldown),
+}
+
+impl std::fmt::Display for CastRefusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CastRefusal::OutOfRange => write!(f, "too far away"),
+            CastRefusal::OutOfSight => write!(f, "out of sight"),
+            CastRefusal::NotEnoughMana => write!(f, "not enough mana"),
+            CastRefusal::NotInHand => write!(f, "no card in hand"),
+            CastRefusal::Recharging(Cooldown::Turns(turns)) => {
+                write!(f, "recharging for {turns} more turns")
+            }
+            CastRefusal::Recharging(Cooldown::Seconds(seconds)) => {
+                write!(f, "recharging for {seconds:.1} more seconds")
             }
         }
     }
 }
 
+impl RonAsset for SpellDef {
+    const EXTENSIONS: &'static [&'static str] = &["spell.ron"];
+}
+
+impl NamedAsset for SpellDef {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Every spell definition.
+pub type SpellDefs = AssetFolder<SpellDef>;
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct SpellLabel;
+
 pub struct SpellPlugin;
 
 impl Plugin for SpellPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(recieved_spell.exclusive_system())
-            .add_system(cast_spell::cast_spell)
-            .add_event::<SpellCast>();
+        app.add_asset::<SpellDef>()
+            .init_asset_loader::<RonLoader<SpellDef>>()
+            .add_startup_system(load_spell_defs)
+            .add_system(
+                recieved_spell
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(SpellLabel)
+                    .after(TurnLabel::Input),
+            )
+            .add_system(
+                cast_spell::cast_spell
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .run_if(player_can_act)
+                    .label(TurnLabel::Input),
+            )
+            .add_event::<SpellCast>()
+            .add_event::<CastRefused>();
+    }
+}
+
+fn load_spell_defs(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SpellDefs::load(&asset_server, "spells"));
+}
+
+/// The direction that leads most directly from one tile to another.
+fn direction_between(from: TilePos, to: TilePos) -> Option<Direction> {
+    Direction::from_offset(tile_offset(from, to).signum())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn recieved_spell(
+    mut commands: Commands,
+    mut casts: EventReader<SpellCast>,
+    assets: Res<Assets<SpellDef>>,
+    mut entities: Query<(Entity, &mut PassiveTilePos, &Transform), With<Health>>,
+    factions: Query<&Faction>,
+    mut tiles: MapTiles,
+    mut damage: EventWriter<DamageEvent>,
+    mut heals: EventWriter<HealEvent>,
+    mut statuses: EventWriter<ApplyStatus>,
+    mut fire: EventWriter<FireProjectile>,
+    mut summons: EventWriter<SpawnEnemy>,
+    mut terrain: EventWriter<AlterTerrain>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for spell in casts.iter() {
+        let def = match assets.get(&spell.spell) {
+            Some(def) => def,
+            None => continue,
+        };
+
+        let caster_pos = match entities.get(spell.caster) {
+            Ok((_, pos, _)) => **pos,
+            Err(_) => continue,
+        };
+
+        let faction_of = |entity| factions.get(entity).copied().unwrap_or_default();
+        let caster_faction = faction_of(spell.caster);
+        // Chains only jump between actors the spell affects.
+        let actors = entities
+            .iter()
+            .filter(|(entity, _, _)| {
+                *entity != spell.caster
+                    && def
+                        .affects
+                        .includes(caster_faction, false, faction_of(*entity))
+            })
+            .map(|(_, pos, _)| **pos)
+            .collect::<Vec<_>>();
+        let area = def
+            .area
+            .tiles(caster_pos, spell.position, &actors, &mut tiles);
+        let affected = entities
+            .iter()
+            .filter(|(entity, pos, _)| {
+                area.contains(pos)
+                    && def.affects.includes(
+                        caster_faction,
+                        *entity == spell.caster,
+                        faction_of(*entity),
+                    )
+            })
+            .map(|(entity, _, _)| entity)
+            .collect::<Vec<_>>();
+
+        for effect in &def.effects {
+            match effect {
+                SpellEffect::Damage { amount, kind } => {
+                    for target in &affected {
+                        damage.send(DamageEvent {
+                            source: Some(spell.caster),
+                            target: *target,
+                            amount: rng.gen_range(amount.0..=amount.1.max(amount.0)),
+                            kind: *kind,
+                        });
+                    }
+                }
+                SpellEffect::Heal(amount) => {
+                    for target in &affected {
+                        heals.send(HealEvent {
+                            source: Some(spell.caster),
+                            target: *target,
+                            amount: *amount,
+                        });
+                    }
+                }
+                SpellEffect::Status(effect) => {
+                    for target in &affected {
+                        statuses.send(ApplyStatus {
+                            target: *target,
+                            effect: effect.with_source(spell.caster),
+                        });
+                    }
+                }
+                SpellEffect::Push(distance) => {
+                    for target in &affected {
+                        push(
+                            &mut commands,
+                            &mut entities,
+                            &mut tiles,
+                            *target,
+                            spell.position,
+                            *distance,
+                        );
+                    }
+                }
+                SpellEffect::Summon { name, count } => {
+                    let free = area
+                        .iter()
+                        .filter(|pos| {
+                            tiles.is_walkable(**pos)
+                                && !entities.iter().any(|(_, other, _)| **other == **pos)
+                        })
+                        .take(*count as usize)
+                        .copied()
+                        .collect::<Vec<_>>();
+
+                    for pos in free {
+                        summons.send(SpawnEnemy {
+                            name: name.clone(),
+                            pos,
+                            ally: caster_faction == Faction::Player,
+                        });
+                    }
+                }
+                SpellEffect::Teleport => {
+                    let occupied = entities
+                        .iter()
+                        .any(|(_, other, _)| **other == spell.position);
+
+                    if !tiles.is_walkable(spell.position) || occupied {
+                        info!("Nowhere to land");
+                        continue;
+                    }
+
+                    if let Ok((_, mut pos, transform)) = entities.get_mut(spell.caster) {
+                        **pos = spell.position;
+                        commands.entity(spell.caster).insert(Transform {
+                            translation: trans_from_tile(&spell.position)
+                                .extend(transform.translation.z),
+                            ..*transform
+                        });
+                    }
+                }
+                SpellEffect::Projectile(projectile) => fire.send(FireProjectile {
+                    source: Some(spell.caster),
+                    faction: caster_faction,
+                    affects: def.affects,
+                    from: caster_pos,
+                    target: spell.position,
+                    projectile: projectile.clone(),
+                }),
+                SpellEffect::Reshape { from, to, turns } => {
+                    for pos in area.iter().copied() {
+                        let occupied = entities.iter().any(|(_, other, _)| **other == pos);
+
+                        if tiles.terrain(pos) != Some(*from) || (*to != Terrain::Floor && occupied)
+                        {
+                            continue;
+                        }
+
+                        terrain.send(AlterTerrain {
+                            pos,
+                            terrain: *to,
+                            turns: *turns,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Pushes an actor away from a tile, one tile at a time until something is in the way.
+fn push(
+    commands: &mut Commands,
+    entities: &mut Query<(Entity, &mut PassiveTilePos, &Transform), With<Health>>,
+    tiles: &mut MapTiles,
+    target: Entity,
+    from: TilePos,
+    distance: u32,
+) {
+    let (start, transform) = match entities.get(target) {
+        Ok((_, pos, transform)) => (**pos, *transform),
+        Err(_) => return,
+    };
+
+    // Something standing right on the center has no direction to go in.
+    let direction = match direction_between(from, start) {
+        Some(direction) => direction,
+        None => return,
+    };
+
+    let mut end = start;
+    for _ in 0..distance {
+        let next = match offset_tile(&end, direction.offset()) {
+            Some(next) => next,
+            None => break,
+        };
+
+        if !tiles.is_walkable(next) || entities.iter().any(|(_, other, _)| **other == next) {
+            break;
+        }
+        end = next;
+    }
+
+    if end == start {
+        return;
+    }
+
+    if let Ok((_, mut pos, _)) = entities.get_mut(target) {
+        **pos = end;
+    }
+
+    commands.entity(target).insert(transform.ease_to(
+        Transform {
+            translation: trans_from_tile(&end).extend(transform.translation.z),
+            ..transform
+        },
+        EaseFunction::QuadraticOut,
+        EasingType::Once {
+            duration: PUSH_DURATION,
+        },
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn shape(area: Area, caster: (u32, u32), target: (u32, u32)) -> HashSet<TilePos> {
+        shape_among(area, caster, target, &[])
+    }
+
+    /// The shape of an area with nothing in the way, and actors standing at `actors`.
+    fn shape_among(
+        area: Area,
+        caster: (u32, u32),
+        target: (u32, u32),
+        actors: &[(u32, u32)],
+    ) -> HashSet<TilePos> {
+        let actors = actors
+            .iter()
+            .map(|(x, y)| TilePos(*x, *y))
+            .collect::<Vec<_>>();
+
+        area.shape(
+            TilePos(caster.0, caster.1),
+            TilePos(target.0, target.1),
+            &actors,
+            |_, _| true,
+        )
+        .into_iter()
+        .collect()
+    }
+
+    fn tiles(tiles: &[(u32, u32)]) -> HashSet<TilePos> {
+        tiles.iter().map(|(x, y)| TilePos(*x, *y)).collect()
+    }
+
+    #[test]
+    fn tile_is_only_the_target() {
+        assert_eq!(shape(Area::Tile, (0, 0), (3, 4)), tiles(&[(3, 4)]));
+    }
+
+    #[test]
+    fn square_surrounds_the_target() {
+        let square = shape(Area::Square(1), (0, 0), (5, 5));
+
+        assert_eq!(square.len(), 9);
+        assert!(square.contains(&TilePos(4, 4)));
+        assert!(square.contains(&TilePos(6, 6)));
+    }
+
+    #[test]
+    fn square_is_clamped_at_the_map_origin() {
+        assert_eq!(
+            shape(Area::Square(1), (3, 3), (0, 0)),
+            tiles(&[(0, 0), (0, 1), (1, 0), (1, 1)])
+        );
+    }
+
+    #[test]
+    fn line_starts_next_to_the_caster_and_goes_past_the_target() {
+        assert_eq!(
+            shape(Area::Line(4), (1, 1), (2, 1)),
+            tiles(&[(2, 1), (3, 1), (4, 1), (5, 1)])
+        );
+        assert_eq!(
+            shape(Area::Line(2), (1, 1), (5, 5)),
+            tiles(&[(2, 2), (3, 3)])
+        );
+    }
+
+    #[test]
+    fn line_at_the_caster_is_empty() {
+        assert!(shape(Area::Line(3), (2, 2), (2, 2)).is_empty());
+    }
+
+    #[test]
+    fn cone_widens_towards_the_target() {
+        let cone = shape(Area::Cone(2), (5, 5), (6, 5));
+
+        for inside in [(6, 5), (7, 4), (7, 5), (7, 6)] {
+            assert!(cone.contains(&TilePos(inside.0, inside.1)), "{inside:?}");
+        }
+        for outside in [(5, 5), (4, 5), (5, 6), (5, 4), (8, 5)] {
+            assert!(
+                !cone.contains(&TilePos(outside.0, outside.1)),
+                "{outside:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn cone_at_the_caster_is_empty() {
+        assert!(shape(Area::Cone(3), (2, 2), (2, 2)).is_empty());
+    }
+
+    #[test]
+    fn ring_of_zero_is_the_target() {
+        assert_eq!(shape(Area::Ring(0), (0, 0), (4, 4)), tiles(&[(4, 4)]));
+    }
+
+    #[test]
+    fn ring_leaves_out_the_inside() {
+        let ring = shape(Area::Ring(1), (0, 0), (4, 4));
+
+        assert_eq!(ring.len(), 8);
+        assert!(!ring.contains(&TilePos(4, 4)));
+
+        let circle = shape(Area::Circle(2), (0, 0), (4, 4));
+        let ring = shape(Area::Ring(2), (0, 0), (4, 4));
+        let inside = shape(Area::Circle(1), (0, 0), (4, 4));
+
+        assert!(ring.is_disjoint(&inside));
+        assert_eq!(ring.union(&inside).copied().collect::<HashSet<_>>(), circle);
+    }
+
+    #[test]
+    fn chain_jumps_to_the_nearest_actor_in_range() {
+        let chain = Area::Chain { jumps: 3, range: 2 };
+        let actors = [(2, 2), (4, 2), (3, 2), (9, 9)];
+
+        assert_eq!(
+            shape_among(chain, (0, 0), (2, 2), &actors),
+            tiles(&[(2, 2), (3, 2), (4, 2)])
+        );
+    }
+
+    #[test]
+    fn chain_needs_an_actor_at_the_target() {
+        let chain = Area::Chain { jumps: 3, range: 2 };
+
+        assert!(shape_among(chain, (0, 0), (2, 2), &[(3, 2)]).is_empty());
+    }
+
+    #[test]
+    fn chain_only_jumps_in_sight() {
+        let chain = Area::Chain { jumps: 3, range: 2 };
+        let actors = [TilePos(2, 2), TilePos(3, 2)];
+        let blocked = chain.shape(TilePos(0, 0), TilePos(2, 2), &actors, |_, to| {
+            to != TilePos(3, 2)
+        });
+
+        assert_eq!(blocked, vec![TilePos(2, 2)]);
     }
 }
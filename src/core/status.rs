@@ -0,0 +1,248 @@
+//! Status effects ticking on actors.
+//! Effects are applied through [`ApplyStatus`] by anything, and tick once a turn, or on the world timer in free roam.
+use bevy::prelude::*;
+use bevy_console::{reply, reply_failed, AddConsoleCommand, ConsoleCommand};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    components::{
+        DamageType, Health, PassiveTilePos, Player, StatusEffect, StatusEffects, StatusKind,
+    },
+    ActiveState, GameState,
+};
+
+use super::{world_ticked, DamageEvent, HealEvent, TileCursor, TurnLabel, TurnState};
+
+/// Adds a status effect to an actor.
+#[derive(Debug, Clone, Copy)]
+pub struct ApplyStatus {
+    pub target: Entity,
+    pub effect: StatusEffect,
+}
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct StatusTickLabel;
+
+/// Applies a status effect to the hovered actor, or the player
+#[derive(ConsoleCommand)]
+#[console_command(name = "status")]
+struct StatusCommand {
+    /// Kind of effect, like burning or stunned
+    kind: String,
+    /// Turns it lasts
+    turns: u32,
+    /// How strong it is, 1 if left out
+    intensity: Option<i32>,
+}
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyStatus>()
+            .add_console_command::<StatusCommand, _, _>(status_command)
+            .add_system(apply_statuses.run_not_in_state(GameState::GeneratingMap))
+            // A round ends when the player's turn comes around again.
+            .add_enter_system(TurnState::Player, skip_lost_turn.before(StatusTickLabel))
+            .add_enter_system(TurnState::Player, tick_statuses.label(StatusTickLabel))
+            .add_system(
+                tick_statuses
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::FreeRoam)
+                    .run_if(world_ticked)
+                    .label(StatusTickLabel)
+                    .after(TurnLabel::Input),
+            );
+    }
+}
+
+fn apply_statuses(
+    mut commands: Commands,
+    mut events: EventReader<ApplyStatus>,
+    mut actors: Query<Option<&mut StatusEffects>, With<Health>>,
+) {
+    // Actors without effects only get the component at the end of the frame.
+    let mut added: Vec<(Entity, StatusEffects)> = vec![];
+
+    for ApplyStatus { target, effect } in events.iter() {
+        let effects = match actors.get_mut(*target) {
+            Ok(effects) => effects,
+            Err(_) => continue,
+        };
+
+        debug!(
+            "{target:?} is now {:?} for {} turns",
+            effect.kind, effect.turns
+        );
+
+        match effects {
+            Some(mut effects) => effects.apply(*effect),
+            None => match added.iter_mut().find(|(entity, _)| entity == target) {
+                Some((_, effects)) => effects.apply(*effect),
+                None => added.push((*target, StatusEffects(vec![*effect]))),
+            },
+        }
+    }
+
+    for (entity, effects) in added {
+        commands.entity(entity).insert(effects);
+    }
+}
+
+/// A stunned player loses the turn, a slowed one every other turn, and the world goes again.
+fn skip_lost_turn(
+    mut commands: Commands,
+    player: Query<&StatusEffects, With<Player>>,
+    game_state: Res<CurrentState<GameState>>,
+    mut skipped_last: Local<bool>,
+) {
+    if game_state.0 != GameState::TurnBased {
+        return;
+    }
+
+    let effects = match player.get_single() {
+        Ok(effects) => effects,
+        Err(_) => return,
+    };
+
+    let skip =
+        effects.has(StatusKind::Stunned) || (effects.has(StatusKind::Slowed) && !*skipped_last);
+    *skipped_last = skip;
+
+    if skip {
+        debug!("The player loses a turn");
+        commands.insert_resource(NextState(TurnState::World));
+    }
+}
+
+/// Deals out the effects that happen every turn, and removes effects that have worn off.
+fn tick_statuses(
+    mut actors: Query<(Entity, &mut StatusEffects)>,
+    mut damage: EventWriter<DamageEvent>,
+    mut heals: EventWriter<HealEvent>,
+) {
+    for (entity, mut effects) in actors.iter_mut() {
+        for effect in effects.iter_mut() {
+            let kind = match effect.kind {
+                StatusKind::Burning => Some(DamageType::Fire),
+                StatusKind::Poisoned => Some(DamageType::Poison),
+                _ => None,
+            };
+
+            if let Some(kind) = kind {
+                damage.send(DamageEvent {
+                    source: effect.source,
+                    target: entity,
+                    amount: effect.intensity,
+                    kind,
+                });
+            }
+
+            if effect.kind == StatusKind::Regenerating {
+                heals.send(HealEvent {
+                    source: effect.source,
+                    target: entity,
+                    amount: effect.intensity,
+                });
+            }
+
+            effect.turns = effect.turns.saturating_sub(1);
+        }
+
+        effects.retain(|effect| {
+            if effect.turns == 0 {
+                debug!("{entity:?} is no longer {:?}", effect.kind);
+            }
+            effect.turns > 0
+        });
+    }
+}
+
+fn status_command(
+    mut status: ConsoleCommand<StatusCommand>,
+    mut writer: EventWriter<ApplyStatus>,
+    cursor: Query<(Entity, &TileCursor), With<Player>>,
+    actors: Query<(Entity, &PassiveTilePos), With<Health>>,
+) {
+    if let Some(StatusCommand {
+        kind,
+        turns,
+        intensity,
+    }) = status.take()
+    {
+        let kind = match kind.parse::<StatusKind>() {
+            Ok(kind) => kind,
+            Err(_) => {
+                reply_failed!(status, "No status effect named '{kind}'");
+                return;
+            }
+        };
+
+        let (player, cursor) = match cursor.get_single() {
+            Ok(player) => player,
+            Err(_) => return,
+        };
+
+        let target = (**cursor)
+            .and_then(|hovered| {
+                actors
+                    .iter()
+                    .find(|(_, pos)| ***pos == hovered)
+                    .map(|(entity, _)| entity)
+            })
+            .unwrap_or(player);
+
+        writer.send(ApplyStatus {
+            target,
+            effect: StatusEffect::new(kind, turns, intensity.unwrap_or(1)),
+        });
+
+        reply!(status, "Applied {kind:?} to {target:?}");
+        status.ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(effects: Vec<StatusEffect>) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_event::<DamageEvent>()
+            .add_event::<HealEvent>()
+            .add_system(tick_statuses);
+
+        let actor = app.world.spawn().insert(StatusEffects(effects)).id();
+        app.update();
+
+        (app, actor)
+    }
+
+    #[test]
+    fn effects_are_removed_when_their_turns_run_out() {
+        let (app, actor) = tick(vec![
+            StatusEffect::new(StatusKind::Slowed, 1, 1),
+            StatusEffect::new(StatusKind::Stunned, 2, 1),
+        ]);
+        let effects = app.world.get::<StatusEffects>(actor).unwrap();
+
+        assert!(!effects.has(StatusKind::Slowed));
+        assert_eq!(effects.get(StatusKind::Stunned).unwrap().turns, 1);
+    }
+
+    #[test]
+    fn effects_act_on_the_turn_they_run_out() {
+        let (app, actor) = tick(vec![
+            StatusEffect::new(StatusKind::Burning, 1, 3),
+            StatusEffect::new(StatusKind::Regenerating, 1, 2),
+        ]);
+        let damage = app.world.resource::<Events<DamageEvent>>();
+        let heals = app.world.resource::<Events<HealEvent>>();
+
+        let burned = damage.get_reader().iter(damage).next().unwrap();
+        assert_eq!((burned.target, burned.amount), (actor, 3));
+        assert_eq!(burned.kind, DamageType::Fire);
+        assert_eq!(heals.get_reader().iter(heals).next().unwrap().amount, 2);
+        assert!(app.world.get::<StatusEffects>(actor).unwrap().is_empty());
+    }
+}
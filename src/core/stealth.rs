@@ -0,0 +1,237 @@
+//! How aware enemies are of the player.
+//! Seeing the player, hearing [`Noise`]s and getting hurt raise an enemy's alertness, which slowly fades otherwise.
+//! Only alert enemies go after the player, suspicious ones look around where something was noticed.
+//! An unaware enemy can be walked up to for about as far as the player can see before it grows suspicious,
+//! so sneak attacks come from out of its sight, and on enemies that see further than the player only while invisible.
+use bevy::prelude::*;
+use bevy_ecs_tilemap::TilePos;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    components::{Enemy, PassiveTilePos, Player, StatusEffects, StatusKind},
+    map::FieldOfView,
+    ActiveState, GameState,
+};
+
+use super::{
+    distance, Action, ActionPerformed, DamageDealt, DamageLabel, SpellCast, TurnLabel, WorldTick,
+};
+
+/// Alertness at which an enemy becomes suspicious.
+const SUSPICIOUS_AT: u32 = 50;
+/// Alertness at which an enemy becomes alert, also the most it can have.
+const ALERT_AT: u32 = 100;
+
+/// Alertness gained every turn the player is seen within half of the view range, and further away.
+const SIGHT_NEAR: u32 = 10;
+const SIGHT_FAR: u32 = 5;
+/// Alertness lost every turn the player is not seen.
+const FADE: u32 = 5;
+/// Alertness gained from a noise for every tile it could have carried further.
+const NOISE_GAIN: u32 = 10;
+
+/// How far the noises the player makes carry.
+const STEP_NOISE: u32 = 1;
+const ATTACK_NOISE: u32 = 6;
+const FIRE_NOISE: u32 = 4;
+const SPELL_NOISE: u32 = 8;
+
+/// How much an enemy knows about the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AwarenessLevel {
+    Unaware,
+    /// Something was noticed, and is being looked into.
+    Suspicious,
+    /// The player has been found.
+    Alert,
+}
+
+#[derive(Debug, Default, Component)]
+pub struct Awareness {
+    /// From 0 up to [`ALERT_AT`].
+    pub alertness: u32,
+    /// Where something was last noticed.
+    pub noticed: Option<TilePos>,
+}
+
+impl Awareness {
+    pub fn level(&self) -> AwarenessLevel {
+        match self.alertness {
+            a if a >= ALERT_AT => AwarenessLevel::Alert,
+            a if a >= SUSPICIOUS_AT => AwarenessLevel::Suspicious,
+            _ => AwarenessLevel::Unaware,
+        }
+    }
+
+    fn raise(&mut self, amount: u32, at: TilePos) {
+        self.alertness = (self.alertness + amount).min(ALERT_AT);
+        self.noticed = Some(at);
+    }
+}
+
+/// A sound that carries `loudness` tiles.
+#[derive(Debug, Clone, Copy)]
+pub struct Noise {
+    pub pos: TilePos,
+    pub loudness: u32,
+    /// Who made it, they don't hear themselves.
+    pub source: Option<Entity>,
+}
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct AwarenessLabel;
+
+pub struct StealthPlugin;
+
+impl Plugin for StealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Noise>()
+            .add_system(
+                make_noise
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .before(AwarenessLabel)
+                    .after(TurnLabel::Resolve),
+            )
+            // Damage goes by how aware its target was before this turn's sights and sounds, so sneak attacks don't
+            // depend on system order.
+            .add_system(
+                watch_for_player
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(AwarenessLabel)
+                    .after(DamageLabel),
+            )
+            .add_system(
+                hear_noises
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(AwarenessLabel)
+                    .after(DamageLabel),
+            )
+            .add_system(
+                alert_when_hurt
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(AwarenessLabel)
+                    .after(DamageLabel),
+            );
+    }
+}
+
+/// The player makes noise by moving, fighting and casting. Anyone fighting makes noise.
+fn make_noise(
+    mut noises: EventWriter<Noise>,
+    mut performed: EventReader<ActionPerformed>,
+    mut casts: EventReader<SpellCast>,
+    player: Query<(Entity, &PassiveTilePos), (With<Player>, Changed<PassiveTilePos>)>,
+    actors: Query<&PassiveTilePos>,
+) {
+    if let Ok((player, pos)) = player.get_single() {
+        noises.send(Noise {
+            pos: **pos,
+            loudness: STEP_NOISE,
+            source: Some(player),
+        });
+    }
+
+    for event in performed.iter() {
+        let loudness = match event.action {
+            Action::Attack(_) => ATTACK_NOISE,
+            Action::Fire(_) => FIRE_NOISE,
+            _ => continue,
+        };
+
+        if let Ok(pos) = actors.get(event.actor) {
+            noises.send(Noise {
+                pos: **pos,
+                loudness,
+                source: Some(event.actor),
+            });
+        }
+    }
+
+    for cast in casts.iter() {
+        noises.send(Noise {
+            pos: cast.position,
+            loudness: SPELL_NOISE,
+            source: Some(cast.caster),
+        });
+    }
+}
+
+/// Once a turn, enemies that see the player grow more alert, the closer the faster. The rest calm down.
+#[allow(clippy::type_complexity)]
+fn watch_for_player(
+    mut enemies: Query<(&PassiveTilePos, &FieldOfView, &mut Awareness), With<Enemy>>,
+    player: Query<(Entity, &PassiveTilePos, Option<&StatusEffects>), With<Player>>,
+    mut performed: EventReader<ActionPerformed>,
+    tick: Res<WorldTick>,
+    game_state: Res<CurrentState<GameState>>,
+) {
+    let (player, player_pos, effects) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    let turn_passed = match game_state.0 {
+        GameState::FreeRoam => tick.just_finished(),
+        _ => performed.iter().any(|event| event.actor == player),
+    };
+
+    if !turn_passed {
+        return;
+    }
+
+    let invisible = effects.map_or(false, |effects| effects.has(StatusKind::Invisible));
+
+    for (pos, fov, mut awareness) in enemies.iter_mut() {
+        if invisible || !fov.tiles.contains(&**player_pos) {
+            awareness.alertness = awareness.alertness.saturating_sub(FADE);
+            continue;
+        }
+
+        let gain = if distance(**pos, **player_pos) * 2 <= fov.range {
+            SIGHT_NEAR
+        } else {
+            SIGHT_FAR
+        };
+
+        awareness.raise(gain, **player_pos);
+    }
+}
+
+fn hear_noises(
+    mut noises: EventReader<Noise>,
+    mut enemies: Query<(Entity, &PassiveTilePos, &mut Awareness), With<Enemy>>,
+) {
+    for noise in noises.iter() {
+        for (entity, pos, mut awareness) in enemies.iter_mut() {
+            let heard_from = distance(**pos, noise.pos);
+
+            if noise.source == Some(entity) || heard_from > noise.loudness {
+                continue;
+            }
+
+            awareness.raise((noise.loudness - heard_from + 1) * NOISE_GAIN, noise.pos);
+        }
+    }
+}
+
+/// Getting hurt makes an enemy alert, and aware of where the hit came from.
+fn alert_when_hurt(
+    mut dealt: EventReader<DamageDealt>,
+    mut enemies: Query<(&PassiveTilePos, &mut Awareness)>,
+    actors: Query<&PassiveTilePos>,
+) {
+    for event in dealt.iter() {
+        if let Ok((pos, mut awareness)) = enemies.get_mut(event.target) {
+            let from = event
+                .source
+                .and_then(|source| actors.get(source).ok())
+                .map_or(**pos, |source| **source);
+
+            awareness.raise(ALERT_AT, from);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Walking along a path over several steps, e.g. to a tile that was clicked on.
+use std::{collections::VecDeque, fmt};
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy_easings::EasingComponent;
+use bevy_ecs_tilemap::TilePos;
+use bevy_rapier2d::prelude::Velocity;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    components::{Enemy, Item, PassiveTilePos, Player},
+    map::{a_star, FieldOfView, MapTiles},
+    util::trans_from_tile,
+    ActiveState, GameState,
+};
+
+use super::{
+    explored_walk_cost, mouse::CurrentMousePosition, step_target, Action, ActionEvent, AutoExplore,
+    CutCornersConvar, Direction, MovementAction, PlayerVelocityLabel, TurnLabel, TurnState,
+    PLAYER_SPEED,
+};
+
+/// How close to the center of a tile the player has to be in free roam before heading for the next one.
+const ARRIVE_DISTANCE: f32 = 2.;
+
+/// A path the player is walking along. Removed once the walk stops, for whatever reason.
+#[derive(Debug, Component)]
+pub struct Travel {
+    path: VecDeque<TilePos>,
+    /// Hostiles that were already in view when the walk started, these don't stop it.
+    known_hostiles: Vec<Entity>,
+}
+
+impl Travel {
+    pub fn new(path: Vec<TilePos>, known_hostiles: Vec<Entity>) -> Self {
+        Self {
+            path: path.into(),
+            known_hostiles,
+        }
+    }
+}
+
+/// Why a walk, or anything else that goes on over several turns, stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Arrived,
+    HostileInView,
+    Blocked,
+    Cancelled,
+    /// There is nothing left to explore.
+    Explored,
+    TookDamage,
+    Healed,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StopReason::Arrived => "arrived",
+            StopReason::HostileInView => "a hostile came into view",
+            StopReason::Blocked => "the path is blocked",
+            StopReason::Cancelled => "cancelled",
+            StopReason::Explored => "there is nothing left to explore",
+            StopReason::TookDamage => "took damage",
+            StopReason::Healed => "fully healed",
+        })
+    }
+}
+
+/// A walk stopped.
+#[derive(Debug, Clone, Copy)]
+pub struct TravelStopped {
+    pub entity: Entity,
+    pub reason: StopReason,
+}
+
+pub struct TravelPlugin;
+
+impl Plugin for TravelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TravelStopped>()
+            .add_system(report_travel_stops)
+            .add_system(
+                click_to_move
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap),
+            )
+            .add_system(
+                travel_turn_based
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::TurnBased)
+                    .run_in_state(TurnState::Player)
+                    .label(TurnLabel::Input),
+            )
+            .add_system(
+                travel_free_roam
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::FreeRoam)
+                    .after(PlayerVelocityLabel),
+            );
+    }
+}
+
+/// Hostiles the player can currently see.
+pub fn visible_hostiles(
+    fov: &FieldOfView,
+    hostiles: &Query<(Entity, &PassiveTilePos), With<Enemy>>,
+) -> Vec<Entity> {
+    hostiles
+        .iter()
+        .filter(|(_, pos)| fov.tiles.contains(&pos.0))
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+pub fn stop_travel(
+    commands: &mut Commands,
+    stopped: &mut EventWriter<TravelStopped>,
+    entity: Entity,
+    reason: StopReason,
+) {
+    commands.entity(entity).remove::<Travel>();
+    stopped.send(TravelStopped { entity, reason });
+}
+
+/// Tells the player why their walk stopped. When exploring, exploration reports it instead.
+fn report_travel_stops(
+    mut stopped: EventReader<TravelStopped>,
+    exploring: Query<(), With<AutoExplore>>,
+) {
+    for TravelStopped { entity, reason } in stopped.iter() {
+        if !exploring.contains(*entity) {
+            info!("Stopped walking: {reason}");
+        }
+    }
+}
+
+/// The next tile to walk to, or why the walk should stop.
+fn next_tile(
+    player: Entity,
+    travel: &Travel,
+    fov: &FieldOfView,
+    hostiles: &Query<(Entity, &PassiveTilePos), With<Enemy>>,
+    actors: &Query<(Entity, &PassiveTilePos), Without<Item>>,
+    tiles: &mut MapTiles,
+) -> Result<TilePos, StopReason> {
+    let next = *travel.path.front().ok_or(StopReason::Arrived)?;
+
+    if visible_hostiles(fov, hostiles)
+        .iter()
+        .any(|hostile| !travel.known_hostiles.contains(hostile))
+    {
+        return Err(StopReason::HostileInView);
+    }
+
+    let occupied = actors
+        .iter()
+        .any(|(entity, pos)| entity != player && **pos == next);
+
+    if occupied || !tiles.is_walkable(next) {
+        return Err(StopReason::Blocked);
+    }
+
+    Ok(next)
+}
+
+/// Walks to the clicked tile, unless the click was on the UI.
+#[allow(clippy::too_many_arguments)]
+fn click_to_move(
+    mut commands: Commands,
+    buttons: Res<Input<MouseButton>>,
+    interactions: Query<&Interaction>,
+    hovered: Res<CurrentMousePosition>,
+    player: Query<(Entity, &PassiveTilePos, &FieldOfView), With<Player>>,
+    hostiles: Query<(Entity, &PassiveTilePos), With<Enemy>>,
+    mut tiles: MapTiles,
+    cut_corners: Res<CutCornersConvar>,
+) {
+    if !buttons.just_pressed(MouseButton::Left)
+        || interactions
+            .iter()
+            .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let (goal, (entity, pos, fov)) = match (**hovered, player.get_single()) {
+        (Some(goal), Ok(player)) => (goal, player),
+        _ => return,
+    };
+
+    // Only walk through tiles the player knows about.
+    let path = a_star(**pos, goal, |from, direction| {
+        explored_walk_cost(&mut tiles, from, direction, cut_corners.on)
+    });
+
+    match path {
+        Some(path) if !path.is_empty() => {
+            commands
+                .entity(entity)
+                .insert(Travel::new(path, visible_hostiles(fov, &hostiles)));
+        }
+        _ => info!("No known path to {goal:?}"),
+    }
+}
+
+/// Takes one step along the path each turn.
+fn travel_turn_based(
+    mut commands: Commands,
+    mut player: Query<
+        (
+            Entity,
+            &PassiveTilePos,
+            &FieldOfView,
+            &mut Travel,
+            &ActionState<MovementAction>,
+        ),
+        (With<Player>, Without<EasingComponent<Transform>>),
+    >,
+    hostiles: Query<(Entity, &PassiveTilePos), With<Enemy>>,
+    actors: Query<(Entity, &PassiveTilePos), Without<Item>>,
+    mut tiles: MapTiles,
+    cut_corners: Res<CutCornersConvar>,
+    mut actions: EventWriter<ActionEvent>,
+    mut stopped: EventWriter<TravelStopped>,
+) {
+    let (entity, pos, fov, mut travel, action_state) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    if MovementAction::ALL
+        .into_iter()
+        .any(|action| action_state.pressed(action))
+    {
+        stop_travel(&mut commands, &mut stopped, entity, StopReason::Cancelled);
+        return;
+    }
+
+    let from = **pos;
+    let step = next_tile(entity, &travel, fov, &hostiles, &actors, &mut tiles).and_then(|next| {
+        Direction::from_offset(IVec2::new(
+            next.0 as i32 - from.0 as i32,
+            next.1 as i32 - from.1 as i32,
+        ))
+        .filter(|direction| step_target(&mut tiles, from, *direction, cut_corners.on).is_some())
+        .ok_or(StopReason::Blocked)
+    });
+
+    match step {
+        Ok(direction) => {
+            travel.path.pop_front();
+            actions.send(ActionEvent {
+                actor: entity,
+                action: Action::Move(direction),
+            });
+        }
+        Err(reason) => stop_travel(&mut commands, &mut stopped, entity, reason),
+    }
+}
+
+/// Steers the player towards the center of the next tile on the path.
+fn travel_free_roam(
+    mut commands: Commands,
+    mut player: Query<
+        (
+            Entity,
+            &Transform,
+            &FieldOfView,
+            &mut Travel,
+            &mut Velocity,
+            &ActionState<MovementAction>,
+        ),
+        With<Player>,
+    >,
+    hostiles: Query<(Entity, &PassiveTilePos), With<Enemy>>,
+    actors: Query<(Entity, &PassiveTilePos), Without<Item>>,
+    mut tiles: MapTiles,
+    mut stopped: EventWriter<TravelStopped>,
+) {
+    let (entity, transform, fov, mut travel, mut velocity, action_state) =
+        match player.get_single_mut() {
+            Ok(player) => player,
+            Err(_) => return,
+        };
+
+    if MovementAction::ALL
+        .into_iter()
+        .any(|action| action_state.pressed(action))
+    {
+        stop_travel(&mut commands, &mut stopped, entity, StopReason::Cancelled);
+        return;
+    }
+
+    let position = transform.translation.xy();
+
+    if let Some(next) = travel.path.front() {
+        if position.distance(trans_from_tile(next)) < ARRIVE_DISTANCE {
+            travel.path.pop_front();
+        }
+    }
+
+    match next_tile(entity, &travel, fov, &hostiles, &actors, &mut tiles) {
+        Ok(next) => {
+            velocity.linvel =
+                (trans_from_tile(&next) - position).normalize_or_zero() * PLAYER_SPEED;
+        }
+        Err(reason) => {
+            velocity.linvel = Vec2::ZERO;
+            stop_travel(&mut commands, &mut stopped, entity, reason);
+        }
+    }
+}
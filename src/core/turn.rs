@@ -0,0 +1,155 @@
+//! Turn based play.
+//! Actors announce what they want to do through [`ActionEvent`]s, which are resolved by the systems responsible for that kind of action.
+use bevy::prelude::*;
+use bevy_easings::EasingComponent;
+use bevy_ecs_tilemap::TilePos;
+use iyes_loopless::prelude::*;
+
+use crate::{components::Player, ActiveState, GameState};
+
+use super::{Direction, Vfx};
+
+/// Whose turn it is. Only meaningful while in [`GameState::TurnBased`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurnState {
+    /// Waiting for the player to act.
+    Player,
+    /// Everything that is not the player acts.
+    World,
+}
+
+/// Something an actor can do with its turn.
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    /// Step one tile.
+    Move(Direction),
+    /// Do nothing for a turn.
+    Wait,
+    /// Look around for anything hidden nearby.
+    Search,
+    /// Hit an adjacent actor.
+    Attack(Entity),
+    /// Fire a ranged attack at a tile.
+    Fire(TilePos),
+    /// Cast a spell or use an item. It is resolved when chosen, this only spends the turn.
+    Cast,
+}
+
+/// An actor wants to perform an action.
+#[derive(Debug, Clone, Copy)]
+pub struct ActionEvent {
+    pub actor: Entity,
+    pub action: Action,
+}
+
+/// An action was carried out, the actor has spent its turn.
+#[derive(Debug, Clone, Copy)]
+pub struct ActionPerformed {
+    pub actor: Entity,
+    pub action: Action,
+}
+
+/// Whether the world has acted yet this world turn. It holds off until the effects of the player's turn are done.
+#[derive(Debug, Default, Deref, DerefMut)]
+pub struct WorldActed(pub bool);
+
+#[derive(SystemLabel, Clone, Copy, Hash, Debug, Eq, PartialEq)]
+pub enum TurnLabel {
+    /// Systems that decide on actions and send [`ActionEvent`]s.
+    Input,
+    /// Systems that carry out actions and send [`ActionPerformed`].
+    Resolve,
+}
+
+pub struct TurnPlugin;
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_loopless_state(TurnState::Player)
+            .init_resource::<WorldActed>()
+            .add_event::<ActionEvent>()
+            .add_event::<ActionPerformed>()
+            .add_enter_system(GameState::TurnBased, reset_turn)
+            .add_enter_system(TurnState::World, start_world_turn)
+            // The world also acts in free roam, so actions are resolved in both.
+            .add_system(
+                resolve_idle
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(TurnLabel::Resolve)
+                    .after(TurnLabel::Input),
+            )
+            .add_system(
+                end_player_turn
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::TurnBased)
+                    .run_in_state(TurnState::Player)
+                    .after(TurnLabel::Resolve),
+            )
+            .add_system(
+                end_world_turn
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::TurnBased)
+                    .run_in_state(TurnState::World)
+                    .after(TurnLabel::Resolve),
+            );
+    }
+}
+
+/// Whether the player may act, in turn based play only during their own turn.
+pub fn player_can_act(
+    game_state: Res<CurrentState<GameState>>,
+    turn_state: Res<CurrentState<TurnState>>,
+) -> bool {
+    game_state.0 != GameState::TurnBased || turn_state.0 == TurnState::Player
+}
+
+fn reset_turn(mut commands: Commands) {
+    commands.insert_resource(NextState(TurnState::Player));
+}
+
+fn start_world_turn(mut acted: ResMut<WorldActed>) {
+    **acted = false;
+}
+
+/// Waiting needs nothing done other than spending the turn.
+fn resolve_idle(
+    mut actions: EventReader<ActionEvent>,
+    mut performed: EventWriter<ActionPerformed>,
+) {
+    for event in actions.iter() {
+        if matches!(event.action, Action::Wait) {
+            performed.send(ActionPerformed {
+                actor: event.actor,
+                action: event.action,
+            });
+        }
+    }
+}
+
+fn end_player_turn(
+    mut commands: Commands,
+    mut performed: EventReader<ActionPerformed>,
+    player: Query<(), With<Player>>,
+) {
+    if performed.iter().any(|action| player.contains(action.actor)) {
+        commands.insert_resource(NextState(TurnState::World));
+    }
+}
+
+/// The world is done once it has acted, and every animation and effect started during its turn has finished.
+fn end_world_turn(
+    mut commands: Commands,
+    animating: Query<(), With<EasingComponent<Transform>>>,
+    effects: Query<(), With<Vfx>>,
+    acted: Res<WorldActed>,
+) {
+    // Animations for actions taken this frame are not inserted until the end of it.
+    if !**acted || acted.is_changed() {
+        return;
+    }
+
+    if animating.is_empty() && effects.is_empty() {
+        commands.insert_resource(NextState(TurnState::Player));
+    }
+}
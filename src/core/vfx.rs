@@ -0,0 +1,272 @@
+//! Visual effects, only there to be looked at.
+//! Spells burst over their area when cast, actors flash when hurt or healed, and the amount floats up above them.
+//! Every effect is a [`Vfx`] entity, removed once its animation is done. Projectile sprites in turn based play are too.
+//! In turn based play the world waits for the effects of the player's turn to finish before acting.
+use std::{collections::HashSet, time::Duration};
+
+use bevy::prelude::*;
+use bevy_easings::{Ease, EaseFunction, EasingComponent, EasingType};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    components::{Health, PassiveTilePos},
+    map::MapTiles,
+    util::{trans_from_tile, TILE_SIZE},
+    ActiveState, GameState,
+};
+
+use super::{DamageDealt, DamageLabel, Healed, SpellCast, SpellDef};
+
+/// How long a spell's burst takes to spread and fade.
+const BURST_DURATION: Duration = Duration::from_millis(350);
+/// How long an actor flashes after being hit or healed.
+const FLASH_DURATION: Duration = Duration::from_millis(250);
+/// How long a number floats, and how far up.
+const FLOAT_DURATION: Duration = Duration::from_millis(700);
+const FLOAT_HEIGHT: f32 = TILE_SIZE;
+
+/// Draw order, above actors and projectiles.
+const BURST_Z: f32 = 1.5;
+const NUMBER_Z: f32 = 3.;
+
+/// A purely visual entity, despawned once its [`Transform`] stops animating.
+#[derive(Debug, Component)]
+pub struct Vfx;
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct VfxLabel;
+
+pub struct VfxPlugin;
+
+impl Plugin for VfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            burst_spells
+                .run_in_state(ActiveState::Playing)
+                .run_not_in_state(GameState::GeneratingMap)
+                .label(VfxLabel),
+        )
+        .add_system(
+            show_hits
+                .run_in_state(ActiveState::Playing)
+                .run_not_in_state(GameState::GeneratingMap)
+                .label(VfxLabel)
+                .after(DamageLabel),
+        )
+        .add_system(remove_finished_effects);
+    }
+}
+
+/// Whether every effect has finished playing.
+pub fn effects_finished(effects: Query<(), With<Vfx>>) -> bool {
+    effects.is_empty()
+}
+
+/// Spreads a square of the spell's color over every tile of its area, fading as it grows.
+fn burst_spells(
+    mut commands: Commands,
+    mut casts: EventReader<SpellCast>,
+    assets: Res<Assets<SpellDef>>,
+    actors: Query<(Entity, &PassiveTilePos), With<Health>>,
+    mut tiles: MapTiles,
+) {
+    for cast in casts.iter() {
+        let def = match assets.get(&cast.spell) {
+            Some(def) => def,
+            None => continue,
+        };
+
+        let color = match def.color {
+            Some(color) => color,
+            None => continue,
+        };
+
+        let caster = match actors.get(cast.caster) {
+            Ok((_, pos)) => **pos,
+            Err(_) => continue,
+        };
+
+        let others = actors
+            .iter()
+            .filter(|(entity, _)| *entity != cast.caster)
+            .map(|(_, pos)| **pos)
+            .collect::<Vec<_>>();
+
+        let mut faded = color;
+        faded.set_a(0.);
+
+        for pos in def.area.tiles(caster, cast.position, &others, &mut tiles) {
+            let sprite = Sprite {
+                color,
+                custom_size: Some(Vec2::splat(TILE_SIZE)),
+                ..Default::default()
+            };
+            let transform = Transform {
+                translation: trans_from_tile(&pos).extend(BURST_Z),
+                scale: Vec3::splat(0.2),
+                ..Default::default()
+            };
+
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: sprite.clone(),
+                    transform,
+                    ..Default::default()
+                })
+                .insert(Vfx)
+                .insert(transform.ease_to(
+                    Transform {
+                        scale: Vec3::ONE,
+                        ..transform
+                    },
+                    EaseFunction::QuadraticOut,
+                    EasingType::Once {
+                        duration: BURST_DURATION,
+                    },
+                ))
+                .insert(sprite.ease_to(
+                    Sprite {
+                        color: faded,
+                        ..sprite.clone()
+                    },
+                    EaseFunction::QuadraticIn,
+                    EasingType::Once {
+                        duration: BURST_DURATION,
+                    },
+                ));
+        }
+    }
+}
+
+/// Flashes an actor's sprite a color, fading back to white.
+fn flash(commands: &mut Commands, entity: Entity, sprite: &Sprite, color: Color) {
+    commands.entity(entity).insert(
+        Sprite {
+            color,
+            ..sprite.clone()
+        }
+        .ease_to(
+            Sprite {
+                color: Color::WHITE,
+                ..sprite.clone()
+            },
+            EaseFunction::QuadraticOut,
+            EasingType::Once {
+                duration: FLASH_DURATION,
+            },
+        ),
+    );
+}
+
+/// Floats a number up from where an actor stands. `stacked` lifts it above others shown at the same time.
+fn float_number(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    at: Vec3,
+    stacked: usize,
+    text: String,
+    color: Color,
+) {
+    let transform = Transform::from_translation(Vec3::new(
+        at.x,
+        at.y + stacked as f32 * TILE_SIZE / 2.,
+        NUMBER_Z,
+    ));
+
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section(
+                text,
+                TextStyle {
+                    font: asset_server.load("fonts/PublicPixel.ttf"),
+                    font_size: 8.0,
+                    color,
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Center,
+                    horizontal: HorizontalAlign::Center,
+                },
+            ),
+            transform,
+            ..Default::default()
+        })
+        .insert(Vfx)
+        .insert(transform.ease_to(
+            Transform {
+                translation: transform.translation + Vec3::Y * FLOAT_HEIGHT,
+                ..transform
+            },
+            EaseFunction::QuadraticOut,
+            EasingType::Once {
+                duration: FLOAT_DURATION,
+            },
+        ));
+}
+
+/// Red flashes and numbers for damage taken, green ones for health restored.
+/// Actors that died are gone by the end of the frame, only their numbers show.
+fn show_hits(
+    mut commands: Commands,
+    mut dealt: EventReader<DamageDealt>,
+    mut healed: EventReader<Healed>,
+    actors: Query<(&Transform, Option<&Sprite>)>,
+    asset_server: Res<AssetServer>,
+) {
+    let dealt = dealt.iter().collect::<Vec<_>>();
+    let killed = dealt
+        .iter()
+        .filter(|event| event.killed)
+        .map(|event| event.target)
+        .collect::<HashSet<_>>();
+
+    let hits = dealt.iter().map(|event| {
+        let (text, color) = match (event.amount, event.sneak_attack) {
+            (0, _) => ("0".to_string(), Color::GRAY),
+            (amount, true) => (format!("{amount}!"), Color::YELLOW),
+            (amount, false) => (amount.to_string(), Color::ORANGE_RED),
+        };
+
+        (event.target, text, color, Color::RED)
+    });
+    let heals = healed.iter().filter(|event| event.amount > 0).map(|event| {
+        (
+            event.target,
+            format!("+{}", event.amount),
+            Color::GREEN,
+            Color::GREEN,
+        )
+    });
+
+    let mut shown = vec![];
+    for (target, text, color, tint) in hits.chain(heals) {
+        let (transform, sprite) = match actors.get(target) {
+            Ok(actor) => actor,
+            Err(_) => continue,
+        };
+
+        if let (Some(sprite), false) = (sprite, killed.contains(&target)) {
+            flash(&mut commands, target, sprite, tint);
+        }
+
+        let stacked = shown.iter().filter(|shown| **shown == target).count();
+        shown.push(target);
+
+        float_number(
+            &mut commands,
+            &asset_server,
+            transform.translation,
+            stacked,
+            text,
+            color,
+        );
+    }
+}
+
+fn remove_finished_effects(
+    mut commands: Commands,
+    effects: Query<Entity, (With<Vfx>, Without<EasingComponent<Transform>>)>,
+) {
+    for entity in effects.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -23,9 +23,13 @@ mod util;
 use std::io::Write;
 
 use crate::core::{
-    MousePlugin, MovementAction, MovementPlugin, PlayerHoveredPlugin, SpellPlugin, TileCursor,
+    AiPlugin, AllyPlugin, CardPlugin, CombatPlugin, CommandAction, CommandPlugin, Deck,
+    EnemyPlugin, ExplorePlugin, Hotbar, HotbarAction, HotbarPlugin, ManaPlugin, MousePlugin,
+    MovementAction, MovementPlugin, PickupPlugin, PlayerHoveredPlugin, ProjectilePlugin,
+    RestPlugin, SpellCharges, SpellPlugin, StatusPlugin, StealthPlugin, TileCursor, TravelPlugin,
+    TurnPlugin, VfxPlugin,
 };
-use bevy::{log::LogPlugin, prelude::*, window::PresentMode};
+use bevy::{asset::AssetServerSettings, log::LogPlugin, prelude::*, window::PresentMode};
 use bevy_console::ConsoleOpen;
 use bevy_easings::EasingsPlugin;
 use bevy_ecs_tilemap::prelude::*;
@@ -34,9 +38,10 @@ use components::*;
 use iyes_loopless::prelude::*;
 use leafwing_input_manager::{
     prelude::{ActionState, InputMap},
+    user_input::UserInput,
     InputManagerBundle,
 };
-use map::{FieldOfView, FovPlugin, MapPlugin, Room};
+use map::{FieldOfView, FovPlugin, MapPlugin, Room, StartingRoom};
 use render::RenderPlugin;
 use ui::*;
 use util::{systems::set_texture_filters_to_nearest, TILE_SIZE};
@@ -49,6 +54,8 @@ pub enum ActiveState {
     Paused,
     /// Console closed. All logic may consider the game active.
     Playing,
+    /// The player has died, nothing happens anymore.
+    GameOver,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -108,18 +115,40 @@ fn main() {
         //TODO: Convar
         .insert_resource(ClearColor(Color::hex("171717").unwrap()))
         // .add_plugins(RetroPlugins::default())
+        // Lets enemy definitions and other assets be edited while the game is running.
+        .insert_resource(AssetServerSettings {
+            watch_for_changes: true,
+            ..Default::default()
+        })
         .add_plugins_with(DefaultPlugins, |group| group.disable::<LogPlugin>())
         .add_plugin(TilemapPlugin)
-        // .add_plugin(EasingsPlugin)
+        .add_plugin(EasingsPlugin)
         //TODO cleanup plugins
         .add_plugin(MousePlugin)
+        .add_plugin(TurnPlugin)
         .add_plugin(MovementPlugin)
+        .add_plugin(TravelPlugin)
+        .add_plugin(CommandPlugin)
+        .add_plugin(ExplorePlugin)
+        .add_plugin(PickupPlugin)
+        .add_plugin(RestPlugin)
+        .add_plugin(AiPlugin)
+        .add_plugin(EnemyPlugin)
+        .add_plugin(AllyPlugin)
+        .add_plugin(CombatPlugin)
+        .add_plugin(StatusPlugin)
+        .add_plugin(ProjectilePlugin)
+        .add_plugin(StealthPlugin)
         .add_plugin(UiPlugin)
         .add_plugin(FovPlugin)
         .add_plugin(PlayerHoveredPlugin)
         .add_plugin(MapPlugin)
         .add_plugin(RenderPlugin)
         .add_plugin(SpellPlugin)
+        .add_plugin(ManaPlugin)
+        .add_plugin(CardPlugin)
+        .add_plugin(HotbarPlugin)
+        .add_plugin(VfxPlugin)
         // todo disable features
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(16.))
         .add_plugin(RapierDebugRenderPlugin::default())
@@ -133,12 +162,24 @@ fn main() {
     app.run();
 }
 
-/// Set up for the initial game state
-fn setup_player(mut commands: Commands, asset_server: Res<AssetServer>, rooms: Query<&Room>) {
-    let font = asset_server.load("fonts/PublicPixel.ttf");
-
+/// Spawns the player in the starting room of the first level, and moves it there on every later one.
+fn setup_player(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    rooms: Query<&Room, With<StartingRoom>>,
+    mut player: Query<(&mut PassiveTilePos, &mut Transform, &mut Velocity), With<Player>>,
+) {
     let room = rooms.single().center();
 
+    if let Ok((mut pos, mut transform, mut velocity)) = player.get_single_mut() {
+        **pos = TilePos(room.x as u32, room.y as u32);
+        transform.translation = Vec3::new(room.x * TILE_SIZE, room.y * TILE_SIZE, 1.0);
+        *velocity = Velocity::zero();
+        return;
+    }
+
+    let font = asset_server.load("fonts/PublicPixel.ttf");
+
     // Create the player entity
     commands
         .spawn_bundle(SpriteBundle {
@@ -150,10 +191,37 @@ fn setup_player(mut commands: Commands, asset_server: Res<AssetServer>, rooms: Q
             ..Default::default()
         })
         .insert(Player)
+        .insert(Faction::Player)
         .insert(PassiveTilePos(TilePos(room.x as u32, room.y as u32)))
         .insert(FieldOfView::new(4))
         .insert(TileCursor::new())
         .insert(Health(100))
+        .insert(MaxHealth(100))
+        .insert(NaturalRegen::every(5))
+        .insert(MeleeAttack {
+            accuracy: 80,
+            damage: (3, 8),
+            inflicts: None,
+        })
+        .insert(Armour(1))
+        .insert(Mana(50))
+        .insert(MaxMana(50))
+        .insert(ManaRegen(2))
+        .insert(Deck::new([
+            "fireball",
+            "fireball",
+            "heal",
+            "magic bolt",
+            "magic bolt",
+            "magic bolt",
+            "frost cone",
+            "chain spark",
+            "summon blob",
+            "dig",
+        ]))
+        .insert(SpellCharges::default())
+        .insert(Hotbar::default())
+        .insert(Inventory::default())
         .insert(RigidBody::Dynamic)
         .insert(Velocity::zero())
         .insert(Friction::new(0.))
@@ -169,6 +237,65 @@ fn setup_player(mut commands: Commands, asset_server: Res<AssetServer>, rooms: Q
                 (KeyCode::W, MovementAction::Up),
                 (KeyCode::A, MovementAction::Left),
                 (KeyCode::D, MovementAction::Right),
+                (KeyCode::Numpad7, MovementAction::UpLeft),
+                (KeyCode::Numpad9, MovementAction::UpRight),
+                (KeyCode::Numpad1, MovementAction::DownLeft),
+                (KeyCode::Numpad3, MovementAction::DownRight),
+                (KeyCode::Y, MovementAction::UpLeft),
+                (KeyCode::U, MovementAction::UpRight),
+                (KeyCode::B, MovementAction::DownLeft),
+                (KeyCode::N, MovementAction::DownRight),
+            ]),
+        })
+        .insert_bundle(InputManagerBundle::<CommandAction> {
+            action_state: ActionState::default(),
+            input_map: InputMap::new([
+                (KeyCode::O, CommandAction::AutoExplore),
+                (KeyCode::Period, CommandAction::Wait),
+                (KeyCode::Numpad5, CommandAction::Wait),
+                (KeyCode::Z, CommandAction::Rest),
+                (KeyCode::X, CommandAction::Search),
+            ]),
+        })
+        .insert_bundle(InputManagerBundle::<HotbarAction> {
+            action_state: ActionState::default(),
+            input_map: InputMap::new([
+                (
+                    UserInput::chord([KeyCode::LShift, KeyCode::Key1]),
+                    HotbarAction::Slot1,
+                ),
+                (
+                    UserInput::chord([KeyCode::LShift, KeyCode::Key2]),
+                    HotbarAction::Slot2,
+                ),
+                (
+                    UserInput::chord([KeyCode::LShift, KeyCode::Key3]),
+                    HotbarAction::Slot3,
+                ),
+                (
+                    UserInput::chord([KeyCode::LShift, KeyCode::Key4]),
+                    HotbarAction::Slot4,
+                ),
+                (
+                    UserInput::chord([KeyCode::LShift, KeyCode::Key5]),
+                    HotbarAction::Slot5,
+                ),
+                (
+                    UserInput::chord([KeyCode::LShift, KeyCode::Key6]),
+                    HotbarAction::Slot6,
+                ),
+                (
+                    UserInput::chord([KeyCode::LShift, KeyCode::Key7]),
+                    HotbarAction::Slot7,
+                ),
+                (
+                    UserInput::chord([KeyCode::LShift, KeyCode::Key8]),
+                    HotbarAction::Slot8,
+                ),
+                (
+                    UserInput::chord([KeyCode::LShift, KeyCode::Key9]),
+                    HotbarAction::Slot9,
+                ),
             ]),
         });
 
@@ -178,7 +305,7 @@ fn setup_player(mut commands: Commands, asset_server: Res<AssetServer>, rooms: Q
             text: Text::with_section(
                 "100",
                 TextStyle {
-                    font,
+                    font: font.clone(),
                     font_size: 30.0,
                     color: Color::DARK_GREEN,
                 },
@@ -200,18 +327,32 @@ fn setup_player(mut commands: Commands, asset_server: Res<AssetServer>, rooms: Q
         })
         .insert(HealthText);
 
-    // Spawn the "Blob"
     commands
-        .spawn_bundle(SpriteBundle {
-            texture: asset_server.load("chars/blob.png"),
-            transform: Transform {
-                translation: Vec3::new(48.0, 16.0, 1.0),
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "50",
+                TextStyle {
+                    font,
+                    font_size: 30.0,
+                    color: Color::MIDNIGHT_BLUE,
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Top,
+                    horizontal: HorizontalAlign::Center,
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(10.),
+                    left: Val::Percent(5.),
+                    ..Default::default()
+                },
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(Blob)
-        .insert(TilePos(0, 0));
+        .insert(ManaText);
 }
 
 fn update_state(
@@ -219,6 +360,11 @@ fn update_state(
     console_open: Res<ConsoleOpen>,
     state: Res<CurrentState<ActiveState>>,
 ) {
+    // The console can still be used, but there is no game to go back to.
+    if state.0 == ActiveState::GameOver {
+        return;
+    }
+
     if console_open.is_changed() && !console_open.is_added() {
         let new_state = if console_open.open {
             ActiveState::Paused
@@ -230,135 +376,3 @@ fn update_state(
         commands.insert_resource(NextState(new_state));
     }
 }
-
-// commands
-//     .spawn_bundle(NodeBundle {
-//         style: Style {
-//             size: bevy::prelude::Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-//             justify_content: JustifyContent::SpaceBetween,
-//             ..Default::default()
-//         },
-//         color: Color::NONE.into(),
-//         ..Default::default()
-//     })
-//     .with_children(|parent| {
-//         parent
-//             .spawn_bundle(ImageBundle {
-//                 style: Style {
-//                     position_type: PositionType::Absolute,
-//                     position: bevy::prelude::Rect {
-//                         left: Val::Percent(50.),
-//                         bottom: Val::Percent(5.),
-//                         ..Default::default()
-//                     },
-//                     size: bevy::prelude::Size::new(Val::Px(64.), Val::Px(96.)),
-//                     ..Default::default()
-//                 },
-//                 image: asset_server.load("cards/fireball.png").into(),
-//                 ..Default::default()
-//             })
-//             .insert(Test)
-//             .with_children(|parent| {
-//                 parent.spawn_bundle(ButtonBundle {
-//                     style: Style {
-//                         size: bevy::prelude::Size::new(Val::Percent(100.), Val::Percent(150.)),
-//                         // center button
-//                         margin: Rect::all(Val::Auto),
-//                         justify_content: JustifyContent::Center,
-//                         align_items: AlignItems::Center,
-//                         ..Default::default()
-//                     },
-//                     color: Color::NONE.into(),
-//                     ..Default::default()
-//                 });
-//             });
-//     });
-
-// fn easing_q(
-//     mut commands: Commands,
-//     // mut query: Query<(Entity, &mut Sprite), With<Test>>,
-//     mut query: Query<(Entity, &mut Style, &Children), With<Test>>,
-//     mut button_query: Query<&Interaction, With<Button>>,
-//     input: Res<Input<KeyCode>>,
-// ) {
-//     let (ent, style, children) = query.single_mut();
-//     let button = button_query.get_mut(children[0]);
-//     if let Ok(button) = button {
-//         match *button {
-//             Interaction::None => {
-//                 commands.entity(ent).remove::<EasingComponent<Style>>();
-//                 commands.entity(ent).insert(style.clone().ease_to(
-//                     Style {
-//                         size: bevy::prelude::Size::new(Val::Px(64.), Val::Px(96.)),
-//                         position: Rect {
-//                             left: Val::Percent(50.),
-//                             bottom: Val::Percent(5.0),
-//                             ..Default::default()
-//                         },
-//                         ..Default::default()
-//                     },
-//                     EaseFunction::ExponentialOut,
-//                     EasingType::Once {
-//                         duration: Duration::from_millis(400),
-//                     },
-//                 ));
-//                 return;
-//             }
-//             _ => (),
-//         };
-//         info!("{button:?}");
-//         commands.entity(ent).remove::<EasingComponent<Style>>();
-//         commands.entity(ent).insert(style.clone().ease_to(
-//             Style {
-//                 size: bevy::prelude::Size::new(Val::Px(80.), Val::Px(120.)),
-//                 position: Rect {
-//                     left: Val::Percent(50.),
-//                     bottom: Val::Percent(12.5),
-//                     ..Default::default()
-//                 },
-//                 ..Default::default()
-//             },
-//             EaseFunction::ExponentialOut,
-//             EasingType::Once {
-//                 duration: Duration::from_millis(400),
-//             },
-//         ));
-//     } else {
-//     }
-//     // // info!("{entity:?}");
-//     if input.just_pressed(KeyCode::X) {
-//         commands.entity(ent).remove::<EasingComponent<Style>>();
-//         commands.entity(ent).insert(style.clone().ease_to(
-//             Style {
-//                 size: bevy::prelude::Size::new(Val::Px(80.), Val::Px(120.)),
-//                 position: Rect {
-//                     left: Val::Percent(50.),
-//                     bottom: Val::Percent(12.5),
-//                     ..Default::default()
-//                 },
-//                 ..Default::default()
-//             },
-//             EaseFunction::ExponentialOut,
-//             EasingType::Once {
-//                 duration: Duration::from_millis(400),
-//             },
-//         ));
-//     } else if input.just_pressed(KeyCode::O) {
-//         commands.entity(ent).remove::<EasingComponent<Style>>();
-//         commands.entity(ent).insert(style.clone().ease_to(
-//             Style {
-//                 size: bevy::prelude::Size::new(Val::Px(64.), Val::Px(96.)),
-//                 position: Rect {
-//                     left: Val::Percent(50.),
-//                     bottom: Val::Percent(5.0),
-//                     ..Default::default()
-//                 },
-//                 ..Default::default()
-//             },
-//             EaseFunction::ExponentialOut,
-//             EasingType::Once {
-//                 duration: Duration::from_millis(400),
-//             },
-//         ));
-//     }
-// }
@@ -3,16 +3,19 @@
 use bevy_ecs_tilemap::prelude::*;
 use core::panic;
 use rand::Rng;
-use std::cmp::{max, min, Ordering};
+use std::cmp::{max, min};
 use std::ops::Range;
 
-use crate::map::{Floor, TilePaint, Wall};
+use crate::map::{Exit, Floor, TilePaint, Wall, Water};
 use crate::util::{CHUNK_SIZE, TILE_SIZE};
 
 use bevy::prelude::*;
 
 use super::{Room, TileRect};
 
+/// One in this many rooms gets a pool of water, never the starting room.
+const POOL_CHANCE: u32 = 4;
+
 /// Create tilemaps.
 pub struct MapBuilder {
     /// The size of the map to be generated, in chunks.
@@ -63,8 +66,8 @@ impl MapBuilder {
     //     self
     // }
 
-    /// Build a random map.
-    pub fn build(&self, commands: &mut Commands) -> (LayerBuilder<TileBundle>, Room) {
+    /// Build a random map. Returns the rooms, the first one is where the player starts.
+    pub fn build(&self, commands: &mut Commands) -> (LayerBuilder<TileBundle>, Vec<Room>) {
         debug!(
             "Generating map with size: x: {:?}, y: {:?}",
             self.map_size.0, self.map_size.1
@@ -122,7 +125,12 @@ impl MapBuilder {
                         },
                         ..Default::default()
                     },
-                )
+                );
+
+                // Tunnels are dug after the pool, so one running through the room crosses it on floor.
+                if !rooms.is_empty() && rng.gen_range(0..POOL_CHANCE) == 0 {
+                    fill_pool(&mut layer_builder, &new_room, &mut rng);
+                }
             }
 
             if !rooms.is_empty() {
@@ -203,13 +211,25 @@ impl MapBuilder {
                     .entity(ent.unwrap())
                     .insert(Floor)
                     .insert(TilePaint::Invisible),
+                40 => commands
+                    .entity(ent.unwrap())
+                    .insert(Water)
+                    .insert(TilePaint::Invisible),
                 _ => panic!(),
             };
 
             *data = Some(stolen);
         });
 
-        (layer_builder, Room(rooms.remove(0)))
+        // The way out is as far along the chain of rooms from the start as possible.
+        let last = rooms[rooms.len() - 1].center();
+        if let Ok(exit) =
+            layer_builder.get_tile_entity(commands, TilePos(last.x as u32, last.y as u32))
+        {
+            commands.entity(exit).insert(Exit);
+        }
+
+        (layer_builder, rooms.into_iter().map(Room).collect())
     }
 }
 
@@ -241,71 +261,35 @@ impl MapBuilder {
 //             }
 //         }
 //     }
-
-//     /// A* implemented from <https://www.redblobgames.com/pathfinding/a-star/introduction.html>
-//     pub fn a_star(&self, start: TilePos, goal: TilePos) -> Vec<TilePos> {
-//         let mut frontier = BinaryHeap::new();
-//         let start_TilePos = self.get_tile(&start).unwrap().position;
-//         frontier.push(Reverse(TilePriority {
-//             TilePos: &start_TilePos,
-//             priority: 0,
-//         }));
-
-//         let mut came_from = HashMap::new();
-//         came_from.insert(&start_TilePos, &start_TilePos); // First just TilePoss to itself
-
-//         let mut cost_so_far = HashMap::new();
-//         cost_so_far.insert(&start_TilePos, 0);
-
-//         while let Some(current) = frontier.pop() {
-//             // Pop returns PiecePriority, need the tile itself.
-//             let current = current.0.TilePos;
-
-//             if *current == goal {
-//                 break;
-//             }
-
-//             for neighbour in self.get_neighbours(current) {
-//                 // Neighbour is not in came_from
-//                 let new_cost = cost_so_far.get(current).unwrap() + neighbour.cost;
-//                 // if !came_from.contains_key(neighbour) {
-//                 if !cost_so_far.contains_key(&neighbour.position)
-//                     || new_cost < *cost_so_far.get(&neighbour.position).unwrap()
-//                 {
-//                     // Make sure its not a wall, etc.
-//                     if neighbour.is_safe() {
-//                         cost_so_far.insert(&neighbour.position, new_cost);
-//                         let priority = new_cost + Map::heuristic(goal, neighbour.position);
-//                         frontier.push(Reverse(TilePriority {
-//                             TilePos: &neighbour.position,
-//                             priority,
-//                         }));
-//                         came_from.insert(&neighbour.position, current);
-//                     }
-//                 }
-//             }
-//         }
-
-//         // Came_from now includes the correct path, that can be traced back from came_from[goal]
-//         let mut path = Vec::new();
-
-//         let mut current = self.get_tile(&goal).unwrap().position;
-
-//         while current != start_TilePos {
-//             path.push(current);
-//             current = *came_from.remove(&current).unwrap();
-//         }
-
-//         path.reverse();
-
-//         path
-//     }
-
-//     fn heuristic(a: TilePos, b: TilePos) -> i32 {
-//         i32::abs(a.x - b.x) + i32::abs(a.y - b.y)
-//     }
 // }
 
+/// Fills a small pool of water somewhere in a room, clear of its edges.
+fn fill_pool(layer_builder: &mut LayerBuilder<TileBundle>, room: &TileRect, rng: &mut impl Rng) {
+    let w = rng.gen_range(1..=2);
+    let h = rng.gen_range(1..=2);
+    let x = rng.gen_range(room.x1 + 1..=room.x2 - 1 - w);
+    let y = rng.gen_range(room.y1 + 1..=room.y2 - 1 - h);
+
+    debug!("Filling a pool at {x}, {y}");
+    for x in x..x + w {
+        for y in y..y + h {
+            layer_builder
+                .set_tile(
+                    TilePos(x, y),
+                    TileBundle {
+                        tile: Tile {
+                            texture_index: 40,
+                            visible: false,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+        }
+    }
+}
+
 impl Default for MapBuilder {
     fn default() -> Self {
         Self {
@@ -317,21 +301,3 @@ impl Default for MapBuilder {
         }
     }
 }
-
-/// A wrapper struct that can be put into a priorityqueue, prioritized by cost, so that I dont have to implement ordering on Tile.
-#[derive(Debug, PartialEq, Eq)]
-struct TilePriority<'a> {
-    tile_pos: &'a TilePos,
-    priority: i32,
-}
-impl<'a> Ord for TilePriority<'a> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.priority.cmp(&other.priority)
-    }
-}
-
-impl<'a> PartialOrd for TilePriority<'a> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
@@ -0,0 +1,170 @@
+//! Static colliders for the walls of the map, and for pits and water, which can't be walked over either.
+//! Walls that can be reached are merged into as few rectangles as possible, which are held by a single compound collider.
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::TilePos;
+use bevy_rapier2d::prelude::Collider;
+
+use crate::{
+    core::Direction,
+    util::{offset_tile, HALF_TILE_SIZE},
+};
+
+use super::{Floor, Pit, Terrain, TerrainAltered, Wall, Water};
+
+/// The entity holding the colliders for every wall on the map.
+#[derive(Debug, Component)]
+pub struct WallColliders;
+
+/// A rectangle of wall tiles, inclusive on both ends.
+#[derive(Debug, Clone, Copy)]
+struct WallRect {
+    x1: u32,
+    x2: u32,
+    y1: u32,
+    y2: u32,
+}
+
+impl WallRect {
+    fn collider(&self) -> (Vec2, f32, Collider) {
+        let center = Vec2::new(
+            (self.x1 + self.x2 + 1) as f32 * HALF_TILE_SIZE,
+            (self.y1 + self.y2 + 1) as f32 * HALF_TILE_SIZE,
+        );
+
+        let half_extents = Vec2::new(
+            (self.x2 - self.x1 + 1) as f32 * HALF_TILE_SIZE,
+            (self.y2 - self.y1 + 1) as f32 * HALF_TILE_SIZE,
+        );
+
+        (center, 0., Collider::cuboid(half_extents.x, half_extents.y))
+    }
+}
+
+/// Rebuilds the wall colliders whenever walls are added or removed, which includes when the map is generated.
+/// Altered terrain is rebuilt in the same frame as the change, going by [`TerrainAltered`] until its components are in.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn build_wall_colliders(
+    mut commands: Commands,
+    mut altered: EventReader<TerrainAltered>,
+    added: Query<(), Or<(Added<Wall>, Added<Pit>, Added<Water>)>>,
+    removed_walls: RemovedComponents<Wall>,
+    removed_pits: RemovedComponents<Pit>,
+    removed_water: RemovedComponents<Water>,
+    walls: Query<&TilePos, Or<(With<Wall>, With<Pit>, With<Water>)>>,
+    floors: Query<&TilePos, With<Floor>>,
+    old: Query<Entity, With<WallColliders>>,
+) {
+    let removed = removed_walls.iter().next().is_some()
+        || removed_pits.iter().next().is_some()
+        || removed_water.iter().next().is_some();
+    let altered = altered
+        .iter()
+        .map(|event| (event.pos, event.terrain))
+        .collect::<HashMap<_, _>>();
+
+    if added.is_empty() && !removed && altered.is_empty() {
+        return;
+    }
+
+    for entity in old.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let mut floors = floors.iter().copied().collect::<HashSet<_>>();
+    let mut walls = walls.iter().copied().collect::<HashSet<_>>();
+    for (pos, terrain) in altered {
+        if terrain == Terrain::Floor {
+            walls.remove(&pos);
+            floors.insert(pos);
+        } else {
+            floors.remove(&pos);
+            walls.insert(pos);
+        }
+    }
+
+    // Walls with no floor around them can never be touched.
+    let reachable = walls
+        .iter()
+        .copied()
+        .filter(|wall| {
+            Direction::ALL.into_iter().any(|direction| {
+                offset_tile(wall, direction.offset()).map_or(false, |pos| floors.contains(&pos))
+            })
+        })
+        .collect::<HashSet<_>>();
+
+    let shapes = merge_walls(&reachable)
+        .iter()
+        .map(WallRect::collider)
+        .collect::<Vec<_>>();
+
+    debug!("Built {} wall colliders", shapes.len());
+
+    if shapes.is_empty() {
+        return;
+    }
+
+    commands
+        .spawn_bundle(TransformBundle::identity())
+        .insert(Collider::compound(shapes))
+        .insert(WallColliders);
+}
+
+/// Merges walls into horizontal strips, then stacks strips of the same width into rectangles.
+fn merge_walls(walls: &HashSet<TilePos>) -> Vec<WallRect> {
+    let mut rows: HashMap<u32, Vec<u32>> = HashMap::new();
+    for wall in walls {
+        rows.entry(wall.1).or_default().push(wall.0);
+    }
+
+    let mut ys = rows.keys().copied().collect::<Vec<_>>();
+    ys.sort_unstable();
+
+    let mut done = vec![];
+    // Rectangles that may still grow upwards, keyed by their horizontal extent.
+    let mut open: HashMap<(u32, u32), WallRect> = HashMap::new();
+
+    for y in ys {
+        let xs = rows.get_mut(&y).unwrap();
+        xs.sort_unstable();
+
+        let mut strips = vec![];
+        let mut start = xs[0];
+        let mut end = xs[0];
+        for &x in &xs[1..] {
+            if x == end + 1 {
+                end = x;
+            } else {
+                strips.push((start, end));
+                start = x;
+                end = x;
+            }
+        }
+        strips.push((start, end));
+
+        for (x1, x2) in strips {
+            match open.get_mut(&(x1, x2)) {
+                Some(rect) if rect.y2 + 1 == y => rect.y2 = y,
+                _ => {
+                    if let Some(finished) = open.insert(
+                        (x1, x2),
+                        WallRect {
+                            x1,
+                            x2,
+                            y1: y,
+                            y2: y,
+                        },
+                    ) {
+                        done.push(finished);
+                    }
+                }
+            }
+        }
+    }
+
+    done.extend(open.into_values());
+
+    done
+}
@@ -23,6 +23,10 @@ impl TileRect {
         self.x1 <= other.x2 && self.x2 >= other.x1 && self.y1 <= other.y2 && self.y2 >= other.y1
     }
 
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        self.x1 <= x && x <= self.x2 && self.y1 <= y && y <= self.y2
+    }
+
     pub fn center(&self) -> Vec2 {
         Vec2::new(
             (self.x1 + self.x2) as f32 / 2.,
@@ -32,4 +36,8 @@ impl TileRect {
 }
 
 #[derive(Debug, Component, Deref, DerefMut)]
-pub struct Room(pub TileRect);
\ No newline at end of file
+pub struct Room(pub TileRect);
+
+/// The room the player starts the level in.
+#[derive(Debug, Component)]
+pub struct StartingRoom;
@@ -0,0 +1,208 @@
+//! Dijkstra maps shared by every AI.
+//! Each map holds the cost of walking from every tile to some goal, an actor heads for the goal by stepping downhill.
+//! They are only recomputed when the player moves or the map changes, however many actors use them.
+use std::{collections::HashMap, str::FromStr};
+
+use bevy::prelude::*;
+use bevy_console::ConsoleCommand;
+use bevy_ecs_tilemap::{MapQuery, TilePos};
+use iyes_loopless::prelude::*;
+use strum::EnumString;
+
+use crate::{
+    components::{PassiveTilePos, Player},
+    core::{walk_cost, CutCornersConvar, Direction, TurnLabel},
+    ui::AddConvar,
+    ActiveState, GameState,
+};
+
+use super::{
+    dijkstra, dijkstra_seeded, should_paint_map, Door, Exit, MapPaintLabel, MapTiles, Pit,
+    TerrainAltered, TilePaint, Wall, Water,
+};
+
+/// How much further away fleeing actors try to get, compared to heading straight away from the player.
+/// Above 1 they prefer running past the player to an open area over backing into a dead end.
+const SAFETY_FACTOR: f32 = 1.2;
+
+/// The cost of walking from every reachable tile to the nearest goal.
+#[derive(Debug, Default, Deref)]
+pub struct DijkstraMap(HashMap<TilePos, u32>);
+
+impl DijkstraMap {
+    pub fn get(&self, pos: TilePos) -> Option<u32> {
+        self.0.get(&pos).copied()
+    }
+
+    /// The cheapest neighbouring tile that is cheaper than `pos`, None if no step gets closer to the goal.
+    /// `step` is asked where a step in a direction ends up, None if it can't be taken.
+    pub fn downhill(
+        &self,
+        pos: TilePos,
+        mut step: impl FnMut(Direction) -> Option<TilePos>,
+    ) -> Option<TilePos> {
+        let current = self.get(pos)?;
+
+        Direction::ALL
+            .into_iter()
+            .filter_map(&mut step)
+            .filter_map(|target| self.get(target).map(|cost| (target, cost)))
+            .filter(|(_, cost)| *cost < current)
+            .min_by_key(|(_, cost)| *cost)
+            .map(|(target, _)| target)
+    }
+}
+
+/// The Dijkstra maps every AI shares.
+#[derive(Debug, Default)]
+pub struct DijkstraMaps {
+    /// Leads to the player.
+    pub player: DijkstraMap,
+    /// Leads to the nearest [`Exit`].
+    pub exit: DijkstraMap,
+    /// Leads away from the player, towards places with room to keep running.
+    pub safety: DijkstraMap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum DijkstraMapKind {
+    Player,
+    Exit,
+    Safety,
+}
+
+/// Draws a Dijkstra map over the tiles
+#[derive(ConsoleCommand)]
+#[console_command(name = "dijkstra_overlay")]
+pub struct DijkstraOverlayConvar {
+    /// Map to draw: player, exit or safety. Leave out to turn the overlay off
+    pub map: Option<String>,
+}
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct DijkstraMapsLabel;
+
+pub struct DijkstraPlugin;
+
+impl Plugin for DijkstraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DijkstraMaps>()
+            .add_convar(DijkstraOverlayConvar { map: None })
+            .add_system(
+                update_dijkstra_maps
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(DijkstraMapsLabel)
+                    .after(TurnLabel::Resolve),
+            )
+            .add_system(
+                draw_dijkstra_overlay
+                    .run_in_state(ActiveState::Playing)
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .after(DijkstraMapsLabel)
+                    .before(MapPaintLabel),
+            );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_dijkstra_maps(
+    mut maps: ResMut<DijkstraMaps>,
+    player_query: Query<&PassiveTilePos, With<Player>>,
+    moved: Query<(), (With<Player>, Changed<PassiveTilePos>)>,
+    added_walls: Query<(), Added<Wall>>,
+    removed_walls: RemovedComponents<Wall>,
+    added_hazards: Query<(), Or<(Added<Pit>, Added<Water>)>>,
+    removed_pits: RemovedComponents<Pit>,
+    removed_water: RemovedComponents<Water>,
+    doors: Query<(), Changed<Door>>,
+    exits: Query<&TilePos, With<Exit>>,
+    mut tiles: MapTiles,
+    cut_corners: Res<CutCornersConvar>,
+) {
+    // Every wall counts as added the first time this runs, after the map is generated.
+    let terrain_changed = !added_walls.is_empty()
+        || removed_walls.iter().next().is_some()
+        || !added_hazards.is_empty()
+        || removed_pits.iter().next().is_some()
+        || removed_water.iter().next().is_some()
+        || !doors.is_empty()
+        || cut_corners.is_changed();
+
+    if terrain_changed {
+        let exits = exits.iter().copied().collect::<Vec<_>>();
+        maps.exit = DijkstraMap(dijkstra(&exits, None, |from, direction| {
+            walk_cost(&mut tiles, from, direction, cut_corners.on)
+        }));
+    }
+
+    let player = match player_query.get_single() {
+        Ok(pos) => **pos,
+        Err(_) => return,
+    };
+
+    if !terrain_changed && moved.is_empty() {
+        return;
+    }
+
+    maps.player = DijkstraMap(dijkstra(&[player], None, |from, direction| {
+        walk_cost(&mut tiles, from, direction, cut_corners.on)
+    }));
+
+    // Flipping the player map makes the furthest tiles the cheapest, rescanning it lets actors run around the player
+    // when that leads somewhere further away.
+    let furthest = maps.player.values().copied().max().unwrap_or(0) as f32 * SAFETY_FACTOR;
+    let seeds = maps
+        .player
+        .iter()
+        .map(|(pos, cost)| (*pos, (furthest - *cost as f32 * SAFETY_FACTOR) as u32))
+        .collect::<Vec<_>>();
+
+    maps.safety = DijkstraMap(dijkstra_seeded(&seeds, None, |from, direction| {
+        walk_cost(&mut tiles, from, direction, cut_corners.on)
+    }));
+
+    debug!("Updated dijkstra maps around {player:?}");
+}
+
+/// Paints every tile of the chosen map, from red close to the goal to blue far away.
+fn draw_dijkstra_overlay(
+    overlay: Res<DijkstraOverlayConvar>,
+    maps: Res<DijkstraMaps>,
+    player_q: Query<(), (With<Player>, Changed<PassiveTilePos>)>,
+    mut altered: EventReader<TerrainAltered>,
+    mut map: MapQuery,
+    mut tiles_query: Query<&mut TilePaint>,
+) {
+    // Paints are only drawn when the map is repainted, and cleared right after.
+    if !should_paint_map(&player_q, &overlay, &maps, &mut altered) {
+        return;
+    }
+
+    let kind = match overlay.map.as_deref().map(DijkstraMapKind::from_str) {
+        Some(Ok(kind)) => kind,
+        Some(Err(_)) => {
+            warn!("No such dijkstra map, expected player, exit or safety");
+            return;
+        }
+        None => return,
+    };
+
+    let dijkstra_map = match kind {
+        DijkstraMapKind::Player => &maps.player,
+        DijkstraMapKind::Exit => &maps.exit,
+        DijkstraMapKind::Safety => &maps.safety,
+    };
+
+    let max = dijkstra_map.values().copied().max().unwrap_or(0).max(1) as f32;
+
+    for (pos, cost) in dijkstra_map.iter() {
+        let t = *cost as f32 / max;
+
+        if let Ok(ent) = map.get_tile_entity(*pos, 0, 0) {
+            if let Ok(mut current) = tiles_query.get_mut(ent) {
+                *current = current.greater_of(TilePaint::Debug(Color::rgb(1. - t, 0.2, t)));
+            }
+        }
+    }
+}
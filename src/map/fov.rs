@@ -10,7 +10,7 @@ use crate::{
 
 use iyes_loopless::prelude::*;
 
-use super::{TilePaint, Wall};
+use super::{Explored, TilePaint, Wall};
 
 /// What something can currently see.
 #[derive(Debug, Component)]
@@ -36,7 +36,7 @@ pub struct FovPlugin;
 impl Plugin for FovPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(
-            player_fov
+            update_fovs
                 .run_in_state(ActiveState::Playing)
                 .run_not_in_state(GameState::GeneratingMap)
                 .label(FovCalculationLabel),
@@ -51,6 +51,7 @@ impl Plugin for FovPlugin {
 }
 
 fn update_tile_paint(
+    mut commands: Commands,
     mut player_query: Query<&FieldOfView, (With<Player>, Changed<PassiveTilePos>)>,
     mut map: MapQuery,
     mut tile_query: Query<&mut TilePaint>,
@@ -65,36 +66,45 @@ fn update_tile_paint(
         for tile in &fov.tiles {
             if let Ok(ent) = map.get_tile_entity(*tile, 0, 0) {
                 let mut current = tile_query.get_mut(ent).unwrap();
-                *current = current.greater_of(TilePaint::Visible)
+                *current = current.greater_of(TilePaint::Visible);
+                commands.entity(ent).insert(Explored);
             }
         }
     }
 }
 
 // TODO: Improve this and all subsequent functions greatly. Performance can certainly be improved.
-fn player_fov(
-    map: MapQuery,
+/// Recalculates what everything with a [`FieldOfView`] can see after it moves.
+fn update_fovs(
+    mut map: MapQuery,
     wall_q: Query<(Entity, &mut Tile), With<Wall>>,
     // mut level: ResMut<Level>,
-    mut player_query: Query<
-        (&PassiveTilePos, &mut FieldOfView),
-        (With<Player>, Changed<PassiveTilePos>),
-    >,
+    mut fov_query: Query<(&PassiveTilePos, &mut FieldOfView), Changed<PassiveTilePos>>,
 ) {
-    if let Ok((player_pos, mut player_fov)) = player_query.get_single_mut() {
-        update_visible(map, **player_pos, &mut player_fov, wall_q);
+    for (pos, mut fov) in fov_query.iter_mut() {
+        update_visible(&mut map, **pos, &mut fov, &wall_q);
     }
 }
 
 pub fn update_visible(
-    mut map: MapQuery,
+    map: &mut MapQuery,
     init_position: TilePos,
     fov: &mut FieldOfView,
-    wall_q: Query<(Entity, &mut Tile), With<Wall>>,
+    wall_q: &Query<(Entity, &mut Tile), With<Wall>>,
+) {
+    update_visible_by(map, init_position, fov, |tile| wall_q.contains(tile));
+}
+
+/// Like [`update_visible`], with `blocks` telling which tile entities can't be seen through.
+pub fn update_visible_by(
+    map: &mut MapQuery,
+    init_position: TilePos,
+    fov: &mut FieldOfView,
+    blocks: impl Fn(Entity) -> bool,
 ) {
     fov.tiles.clear();
     for octant in 0..=7 {
-        update_visible_octant(octant, &mut map, init_position, fov, &wall_q);
+        update_visible_octant(octant, map, init_position, fov, &blocks);
     }
     fov.tiles.push(init_position);
 }
@@ -104,7 +114,7 @@ fn update_visible_octant(
     map: &mut MapQuery,
     init_position: TilePos,
     fov: &mut FieldOfView,
-    wall_q: &Query<(Entity, &mut Tile), With<Wall>>,
+    blocks: &impl Fn(Entity) -> bool,
 ) {
     let mut blocked_fov = BlockedFov::new();
     for row in 1..fov.range as i32 {
@@ -136,7 +146,7 @@ fn update_visible_octant(
                     fov.tiles.push(pos);
                 }
 
-                if visible && wall_q.contains(map.get_tile_entity(pos, 0, 0).unwrap()) {
+                if visible && blocks(map.get_tile_entity(pos, 0, 0).unwrap()) {
                     blocked_fov.add_blocker(blocker);
                 }
             }
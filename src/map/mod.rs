@@ -1,59 +1,100 @@
 //! Modules relating to the levels of the game
 mod builder;
+mod collision;
 mod common;
+mod dijkstra;
 mod fov;
+mod pathfinding;
+mod populate;
+mod terrain;
 mod tile;
 
 use std::cmp::Ordering;
 
 use bevy_ecs_tilemap::{Map, MapQuery, Tile, TilePos};
-use bevy_rapier2d::prelude::Collider;
 pub use builder::*;
+pub use collision::*;
 pub use common::*;
+pub use dijkstra::*;
 pub use fov::*;
+pub use pathfinding::*;
+pub use populate::*;
+pub use terrain::*;
 pub use tile::*;
 
 use bevy::prelude::*;
+use bevy_easings::EasingComponent;
 use iyes_loopless::prelude::*;
 
 use crate::{
-    components::{PassiveTilePos, Player},
-    util::trans_from_tile,
+    components::{Enemy, Item, PassiveTilePos, Player},
+    core::{AutoExplore, CountPrefix, Repeat, ResumeFreeRoam, Travel},
     ActiveState, GameState,
 };
 
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct MapPaintLabel;
+
 pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_enter_system(GameState::GeneratingMap, setup_map)
+        app.add_startup_system(setup_camera)
+            .add_enter_system(GameState::GeneratingMap, clear_level)
+            .add_enter_system(GameState::GeneratingMap, setup_map)
             .add_system(
-                spawn_colliders_for_tiles
+                descend
                     .run_in_state(ActiveState::Playing)
                     .run_not_in_state(GameState::GeneratingMap),
             )
+            .add_system(build_wall_colliders.after(TerrainLabel))
+            .add_plugin(DijkstraPlugin)
+            .add_plugin(PopulatePlugin)
+            .add_plugin(TerrainPlugin)
             .add_system(
                 paint_map
                     .run_in_state(ActiveState::Playing)
                     .run_not_in_state(GameState::GeneratingMap)
-                    .after(FovCalculationLabel),
+                    .label(MapPaintLabel)
+                    .after(FovCalculationLabel)
+                    .after(DijkstraMapsLabel),
             );
     }
 }
 
+/// Whether the map should be repainted this frame.
+pub fn should_paint_map(
+    player_q: &Query<(), (With<Player>, Changed<PassiveTilePos>)>,
+    overlay: &Res<DijkstraOverlayConvar>,
+    maps: &Res<DijkstraMaps>,
+    altered: &mut EventReader<TerrainAltered>,
+) -> bool {
+    // Every event has to be read, or they are seen again next frame.
+    let altered = altered.iter().count() > 0;
+
+    altered
+        || !player_q.is_empty()
+        || overlay.is_changed()
+        || (overlay.map.is_some() && maps.is_changed())
+}
+
 fn paint_map(
-    mut tiles: Query<(&mut TilePaint, &mut Tile, &TilePos)>,
-    player_q: Query<Entity, Changed<PassiveTilePos>>,
+    mut tiles: Query<(&mut TilePaint, &mut Tile, &TilePos, Option<&Explored>)>,
+    player_q: Query<(), (With<Player>, Changed<PassiveTilePos>)>,
+    overlay: Res<DijkstraOverlayConvar>,
+    maps: Res<DijkstraMaps>,
+    mut altered: EventReader<TerrainAltered>,
     mut map: MapQuery,
 ) {
-    if player_q.get_single().is_err() {
+    if !should_paint_map(&player_q, &overlay, &maps, &mut altered) {
         return;
     }
 
-    for (mut paint, mut tile, pos) in tiles.iter_mut() {
+    for (mut paint, mut tile, pos, explored) in tiles.iter_mut() {
         tile.visible = true;
         match *paint {
             TilePaint::CursorDraw(color) => tile.color = color,
+            TilePaint::Debug(color) => tile.color = color,
             TilePaint::Visible => tile.color = Color::WHITE,
             TilePaint::PreviouslySeen => tile.color = Color::GRAY,
             TilePaint::Invisible => tile.visible = false,
@@ -61,9 +102,13 @@ fn paint_map(
 
         map.notify_chunk_for_tile(*pos, 0u16, 0u16);
 
-        if *paint != TilePaint::Invisible {
-            *paint = TilePaint::PreviouslySeen;
-        }
+        // Cursors and overlays may have painted tiles that haven't been seen.
+        // Tiles seen for the first time this frame are only marked explored at the end of it.
+        *paint = if explored.is_some() || *paint == TilePaint::Visible {
+            TilePaint::PreviouslySeen
+        } else {
+            TilePaint::Invisible
+        };
 
         // commands.entity(entity).remove()
     }
@@ -81,45 +126,65 @@ fn paint_map(
     // }
 }
 
-fn spawn_colliders_for_tiles(
-    player: Query<&PassiveTilePos, (With<Player>, Changed<PassiveTilePos>)>,
-    mut map: MapQuery,
-    mut tiles: Query<(Entity, &TilePos), With<Wall>>,
+fn setup_camera(mut commands: Commands) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+}
+
+/// Stepping onto the exit takes the player one level deeper.
+fn descend(
     mut commands: Commands,
+    player: Query<(Entity, &PassiveTilePos), (With<Player>, Changed<PassiveTilePos>)>,
+    exits: Query<&TilePos, With<Exit>>,
+    mut level: ResMut<CurrentLevel>,
 ) {
-    let player = player.get_single();
+    let (entity, pos) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
 
-    if player.is_err() {
+    if !exits.iter().any(|exit| *exit == **pos) {
         return;
     }
 
-    for (tile, _) in tiles.iter() {
-        commands
-            .entity(tile)
-            .remove::<Collider>()
-            .remove::<Transform>()
-            .remove::<GlobalTransform>();
-    }
+    level.depth += 1;
+    info!("Descending to depth {}", level.depth);
 
-    for tile in map.get_tile_neighbors(**player.unwrap(), 0, 0) {
-        if let Ok(tile) = tile {
-            if let Ok((_, pos)) = tiles.get_mut(tile) {
-                let transform = TransformBundle::from_transform(Transform::from_translation(
-                    Vec3::from((trans_from_tile(pos), 0.)),
-                ));
-                commands
-                    .entity(tile)
-                    .insert_bundle(transform)
-                    .insert(Collider::cuboid(8., 8.));
-            }
-        }
+    // Nothing the player was doing carries over.
+    commands
+        .entity(entity)
+        .remove::<EasingComponent<Transform>>()
+        .remove::<Travel>()
+        .remove::<AutoExplore>()
+        .remove::<Repeat>()
+        .remove::<ResumeFreeRoam>()
+        .remove::<CountPrefix>();
+    commands.insert_resource(NextState(GameState::GeneratingMap));
+}
+
+/// Removes the last level, and everything on it, before the next one is generated.
+/// The player, what they carry and the allies that come along are kept.
+#[allow(clippy::type_complexity)]
+fn clear_level(
+    mut commands: Commands,
+    mut map_query: MapQuery,
+    left: Query<
+        Entity,
+        Or<(
+            With<Room>,
+            With<Enemy>,
+            (With<Item>, With<PassiveTilePos>),
+            With<WallColliders>,
+        )>,
+    >,
+) {
+    map_query.despawn(&mut commands, 0u16);
+
+    for entity in left.iter() {
+        commands.entity(entity).despawn_recursive();
     }
 }
 
 fn setup_map(mut commands: Commands, asset_server: Res<AssetServer>, mut map_query: MapQuery) {
-    //TODO: should this be here?
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
-
     // Create map entity and component:
     let map_entity = commands.spawn().id();
     let mut map = Map::new(0u16, map_entity);
@@ -127,7 +192,7 @@ fn setup_map(mut commands: Commands, asset_server: Res<AssetServer>, mut map_que
     let tiles = asset_server.load("tiles/tiles.png");
 
     // Creates a new layer builder with a layer entity.
-    let (layer_builder, room) = MapBuilder::default().build(&mut commands);
+    let (layer_builder, rooms) = MapBuilder::default().build(&mut commands);
 
     // Builds the layer.
     let layer_entity = map_query.build_layer(&mut commands, layer_builder, tiles);
@@ -143,7 +208,12 @@ fn setup_map(mut commands: Commands, asset_server: Res<AssetServer>, mut map_que
         .insert(Transform::from_xyz(0., 0., 0.))
         .insert(GlobalTransform::default());
 
-    commands.spawn().insert(room);
+    for (i, room) in rooms.into_iter().enumerate() {
+        let room = commands.spawn().insert(room).id();
+        if i == 0 {
+            commands.entity(room).insert(StartingRoom);
+        }
+    }
 
     commands.insert_resource(NextState(GameState::FreeRoam))
 }
@@ -155,12 +225,19 @@ impl PartialOrd for TilePaint {
             (TilePaint::CursorDraw(_), _) => Some(Ordering::Greater),
             // (PaintPriority::CursorDraw(_), PaintPriority::PreviouslySeen) => todo!(),
             // (PaintPriority::CursorDraw(_), PaintPriority::Invisible) => todo!(),
+            (TilePaint::Debug(_), TilePaint::Debug(_)) => Some(Ordering::Equal),
+            (TilePaint::Debug(_), TilePaint::CursorDraw(_)) => Some(Ordering::Less),
+            (TilePaint::Debug(_), _) => Some(Ordering::Greater),
             (TilePaint::Visible, TilePaint::Visible) => Some(Ordering::Equal),
-            (TilePaint::Visible, TilePaint::CursorDraw(_)) => Some(Ordering::Less),
+            (TilePaint::Visible, TilePaint::CursorDraw(_) | TilePaint::Debug(_)) => {
+                Some(Ordering::Less)
+            }
             (TilePaint::Visible, _) => Some(Ordering::Greater),
             // (PaintPriority::Visible, PaintPriority::Invisible) => todo!(),
             (TilePaint::PreviouslySeen, TilePaint::PreviouslySeen) => Some(Ordering::Equal),
-            (TilePaint::PreviouslySeen, TilePaint::CursorDraw(_)) => Some(Ordering::Less),
+            (TilePaint::PreviouslySeen, TilePaint::CursorDraw(_) | TilePaint::Debug(_)) => {
+                Some(Ordering::Less)
+            }
             (TilePaint::PreviouslySeen, TilePaint::Visible) => Some(Ordering::Less),
             (TilePaint::PreviouslySeen, TilePaint::Invisible) => Some(Ordering::Greater),
             (TilePaint::Invisible, TilePaint::Invisible) => Some(Ordering::Equal),
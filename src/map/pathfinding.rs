@@ -0,0 +1,325 @@
+//! Pathfinding over the tile grid.
+//! The searches know nothing about the map themselves, what it costs to take a step is decided by the cost function passed in.
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+};
+
+use bevy_ecs_tilemap::TilePos;
+
+use crate::{core::Direction, util::offset_tile};
+
+/// Cost of an orthogonal step.
+pub const STRAIGHT_COST: u32 = 10;
+/// Cost of a diagonal step, roughly sqrt(2) times [`STRAIGHT_COST`].
+pub const DIAGONAL_COST: u32 = 14;
+
+/// The base cost of taking a step in a direction.
+pub fn step_cost(direction: Direction) -> u32 {
+    if direction.split().is_some() {
+        DIAGONAL_COST
+    } else {
+        STRAIGHT_COST
+    }
+}
+
+/// Octile distance, the cheapest a path between two tiles can be if no step costs less than [`step_cost`].
+pub fn heuristic(a: TilePos, b: TilePos) -> u32 {
+    let dx = (a.0 as i32 - b.0 as i32).unsigned_abs();
+    let dy = (a.1 as i32 - b.1 as i32).unsigned_abs();
+
+    STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+}
+
+/// A* implemented from <https://www.redblobgames.com/pathfinding/a-star/introduction.html>
+///
+/// `cost` is asked what it costs to step from a tile in a direction, None means the step can't be taken.
+/// Returns the path to `goal`, not including `start`, or None if there is no path.
+pub fn a_star(
+    start: TilePos,
+    goal: TilePos,
+    mut cost: impl FnMut(TilePos, Direction) -> Option<u32>,
+) -> Option<Vec<TilePos>> {
+    let mut frontier = BinaryHeap::new();
+    frontier.push(Reverse(TilePriority {
+        tile_pos: start,
+        priority: 0,
+    }));
+
+    let mut came_from = HashMap::new();
+    let mut cost_so_far = HashMap::new();
+    cost_so_far.insert(start, 0);
+
+    while let Some(Reverse(TilePriority {
+        tile_pos: current, ..
+    })) = frontier.pop()
+    {
+        if current == goal {
+            return Some(trace_path(&came_from, start, goal));
+        }
+
+        for direction in Direction::ALL {
+            let next = match offset_tile(&current, direction.offset()) {
+                Some(next) => next,
+                None => continue,
+            };
+
+            let step = match cost(current, direction) {
+                Some(step) => step,
+                None => continue,
+            };
+
+            let new_cost = cost_so_far[&current] + step;
+            if cost_so_far.get(&next).map_or(true, |&old| new_cost < old) {
+                cost_so_far.insert(next, new_cost);
+                came_from.insert(next, current);
+                frontier.push(Reverse(TilePriority {
+                    tile_pos: next,
+                    priority: new_cost + heuristic(next, goal),
+                }));
+            }
+        }
+    }
+
+    None
+}
+
+/// Dijkstra's algorithm, flooding out from every source at once.
+///
+/// Returns the cost of the cheapest path from any source to every tile that can be reached, stopping at `max_cost` if given.
+pub fn dijkstra(
+    sources: &[TilePos],
+    max_cost: Option<u32>,
+    cost: impl FnMut(TilePos, Direction) -> Option<u32>,
+) -> HashMap<TilePos, u32> {
+    let seeds = sources
+        .iter()
+        .map(|source| (*source, 0))
+        .collect::<Vec<_>>();
+
+    dijkstra_seeded(&seeds, max_cost, cost)
+}
+
+/// Like [`dijkstra`], but every source starts out with a cost of its own instead of 0.
+pub fn dijkstra_seeded(
+    seeds: &[(TilePos, u32)],
+    max_cost: Option<u32>,
+    mut cost: impl FnMut(TilePos, Direction) -> Option<u32>,
+) -> HashMap<TilePos, u32> {
+    let mut frontier = BinaryHeap::new();
+    let mut cost_so_far = HashMap::new();
+
+    for (source, start) in seeds {
+        if cost_so_far.get(source).map_or(false, |old| old <= start) {
+            continue;
+        }
+
+        cost_so_far.insert(*source, *start);
+        frontier.push(Reverse(TilePriority {
+            tile_pos: *source,
+            priority: *start,
+        }));
+    }
+
+    while let Some(Reverse(TilePriority {
+        tile_pos: current,
+        priority,
+    })) = frontier.pop()
+    {
+        // A cheaper way here was found after this entry was queued.
+        if priority > cost_so_far[&current] {
+            continue;
+        }
+
+        for direction in Direction::ALL {
+            let next = match offset_tile(&current, direction.offset()) {
+                Some(next) => next,
+                None => continue,
+            };
+
+            let step = match cost(current, direction) {
+                Some(step) => step,
+                None => continue,
+            };
+
+            let new_cost = priority + step;
+            if max_cost.map_or(false, |max| new_cost > max) {
+                continue;
+            }
+
+            if cost_so_far.get(&next).map_or(true, |&old| new_cost < old) {
+                cost_so_far.insert(next, new_cost);
+                frontier.push(Reverse(TilePriority {
+                    tile_pos: next,
+                    priority: new_cost,
+                }));
+            }
+        }
+    }
+
+    cost_so_far
+}
+
+/// Came_from includes the path, which can be traced back from the goal.
+fn trace_path(
+    came_from: &HashMap<TilePos, TilePos>,
+    start: TilePos,
+    goal: TilePos,
+) -> Vec<TilePos> {
+    let mut path = Vec::new();
+    let mut current = goal;
+
+    while current != start {
+        path.push(current);
+        current = came_from[&current];
+    }
+
+    path.reverse();
+
+    path
+}
+
+/// A wrapper struct that can be put into a priorityqueue, prioritized by cost, so that I dont have to implement ordering on TilePos.
+#[derive(Debug, PartialEq, Eq)]
+struct TilePriority {
+    tile_pos: TilePos,
+    priority: u32,
+}
+
+impl Ord for TilePriority {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority)
+    }
+}
+
+impl PartialOrd for TilePriority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// Cost function for a `width` by `height` grid, where `walls` can't be stepped on.
+    fn grid(
+        width: u32,
+        height: u32,
+        walls: &[(u32, u32)],
+    ) -> impl FnMut(TilePos, Direction) -> Option<u32> {
+        let walls = walls
+            .iter()
+            .map(|(x, y)| TilePos(*x, *y))
+            .collect::<HashSet<_>>();
+
+        move |from, direction| {
+            let to = offset_tile(&from, direction.offset())?;
+
+            (to.0 < width && to.1 < height && !walls.contains(&to)).then_some(step_cost(direction))
+        }
+    }
+
+    fn path_cost(start: TilePos, path: &[TilePos]) -> u32 {
+        let mut from = start;
+        path.iter()
+            .map(|to| {
+                let cost = heuristic(from, *to);
+                from = *to;
+                cost
+            })
+            .sum()
+    }
+
+    #[test]
+    fn a_star_walks_straight_when_nothing_is_in_the_way() {
+        let path = a_star(TilePos(0, 0), TilePos(3, 0), grid(5, 5, &[])).unwrap();
+
+        assert_eq!(path, vec![TilePos(1, 0), TilePos(2, 0), TilePos(3, 0)]);
+    }
+
+    #[test]
+    fn a_star_goes_around_walls() {
+        // A wall across x = 2, with a gap at the top.
+        let walls = [(2, 0), (2, 1), (2, 2), (2, 3)];
+        let path = a_star(TilePos(0, 0), TilePos(4, 0), grid(5, 5, &walls)).unwrap();
+
+        assert_eq!(path.last(), Some(&TilePos(4, 0)));
+        assert!(path.contains(&TilePos(2, 4)));
+        assert!(path.iter().all(|pos| !walls.contains(&(pos.0, pos.1))));
+    }
+
+    #[test]
+    fn a_star_takes_diagonals_when_cheaper() {
+        let start = TilePos(0, 0);
+        let path = a_star(start, TilePos(3, 3), grid(5, 5, &[])).unwrap();
+
+        assert_eq!(path.len(), 3);
+        assert_eq!(path_cost(start, &path), 3 * DIAGONAL_COST);
+    }
+
+    #[test]
+    fn a_star_finds_no_path_to_walled_in_goal() {
+        let walls = [(3, 3), (3, 4), (4, 3)];
+
+        assert_eq!(
+            a_star(TilePos(0, 0), TilePos(4, 4), grid(5, 5, &walls)),
+            None
+        );
+    }
+
+    #[test]
+    fn a_star_to_start_is_empty() {
+        let path = a_star(TilePos(2, 2), TilePos(2, 2), grid(5, 5, &[])).unwrap();
+
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn dijkstra_costs_diagonals_more_than_straight_steps() {
+        let costs = dijkstra(&[TilePos(0, 0)], None, grid(5, 5, &[]));
+
+        assert_eq!(costs[&TilePos(0, 0)], 0);
+        assert_eq!(costs[&TilePos(2, 0)], 2 * STRAIGHT_COST);
+        assert_eq!(costs[&TilePos(2, 2)], 2 * DIAGONAL_COST);
+        assert_eq!(costs[&TilePos(2, 1)], DIAGONAL_COST + STRAIGHT_COST);
+    }
+
+    #[test]
+    fn dijkstra_goes_around_walls() {
+        let walls = [(1, 0), (1, 1)];
+        let costs = dijkstra(&[TilePos(0, 0)], None, grid(3, 3, &walls));
+
+        // Up, diagonally over the end of the wall and back, then down.
+        assert_eq!(costs[&TilePos(2, 0)], 2 * STRAIGHT_COST + 2 * DIAGONAL_COST);
+        assert!(!costs.contains_key(&TilePos(1, 0)));
+    }
+
+    #[test]
+    fn dijkstra_leaves_out_unreachable_tiles() {
+        let walls = [(3, 3), (3, 4), (4, 3)];
+        let costs = dijkstra(&[TilePos(0, 0)], None, grid(5, 5, &walls));
+
+        assert!(!costs.contains_key(&TilePos(4, 4)));
+        assert_eq!(costs.len(), 5 * 5 - walls.len() - 1);
+    }
+
+    #[test]
+    fn dijkstra_floods_from_the_nearest_source() {
+        let costs = dijkstra(&[TilePos(0, 0), TilePos(4, 0)], None, grid(5, 1, &[]));
+
+        assert_eq!(costs[&TilePos(1, 0)], STRAIGHT_COST);
+        assert_eq!(costs[&TilePos(3, 0)], STRAIGHT_COST);
+        assert_eq!(costs[&TilePos(2, 0)], 2 * STRAIGHT_COST);
+    }
+
+    #[test]
+    fn dijkstra_stops_at_max_cost() {
+        let costs = dijkstra(&[TilePos(0, 0)], Some(2 * STRAIGHT_COST), grid(5, 1, &[]));
+
+        assert!(costs.contains_key(&TilePos(2, 0)));
+        assert!(!costs.contains_key(&TilePos(3, 0)));
+    }
+}
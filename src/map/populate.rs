@@ -0,0 +1,452 @@
+//! Filling a freshly generated level with enemies and items.
+//! What can appear is read from `assets/spawn_table.spawns.ron`, and depends on the depth and theme of the level.
+use std::collections::HashSet;
+
+use bevy::{prelude::*, reflect::TypeUuid};
+use bevy_console::{reply, reply_failed, AddConsoleCommand, ConsoleCommand};
+use bevy_ecs_tilemap::{MapQuery, Tile, TilePos};
+use iyes_loopless::prelude::*;
+use rand::{
+    seq::{IteratorRandom, SliceRandom},
+    Rng,
+};
+use serde::Deserialize;
+
+use crate::{
+    components::PassiveTilePos,
+    core::{SpawnEnemy, SpawnItem},
+    util::{RonAsset, RonLoader},
+    GameState,
+};
+
+use super::{update_visible, FieldOfView, Floor, Room, StartingRoom, Wall};
+
+/// How far from the starting room nothing may be placed, if it can be seen.
+const START_VIEW_RANGE: u32 = 10;
+
+/// Picks that fail to find room before giving up on the rest of the budget.
+const MAX_FAILED_PICKS: u32 = 20;
+
+/// The look and inhabitants of a level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Theme {
+    Dungeon,
+    Crypt,
+    Cave,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dungeon, Theme::Crypt, Theme::Cave];
+}
+
+/// The level currently being played.
+#[derive(Debug)]
+pub struct CurrentLevel {
+    /// How far down the level is, starting at 1.
+    pub depth: u32,
+    pub theme: Theme,
+}
+
+impl Default for CurrentLevel {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            theme: Theme::Dungeon,
+        }
+    }
+}
+
+/// How many points can be spent at a depth.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Budget {
+    pub base: u32,
+    pub per_depth: u32,
+}
+
+impl Budget {
+    pub fn at(&self, depth: u32) -> u32 {
+        self.base + self.per_depth * depth.saturating_sub(1)
+    }
+}
+
+/// Something that can be placed, with how likely it is to be picked.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpawnEntry {
+    /// Name of the enemy or item.
+    pub name: String,
+    pub weight: u32,
+    /// Taken from the budget for every group placed.
+    #[serde(default = "one")]
+    pub cost: u32,
+    #[serde(default = "one")]
+    pub min_depth: u32,
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    /// How many are placed together, at least and at most.
+    #[serde(default = "single")]
+    pub group: (u32, u32),
+    /// Themes this appears in, all of them if empty.
+    #[serde(default)]
+    pub themes: Vec<Theme>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn single() -> (u32, u32) {
+    (1, 1)
+}
+
+impl SpawnEntry {
+    fn allowed(&self, depth: u32, theme: Theme) -> bool {
+        depth >= self.min_depth
+            && self.max_depth.map_or(true, |max| depth <= max)
+            && (self.themes.is_empty() || self.themes.contains(&theme))
+    }
+}
+
+/// What populates levels.
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "b3d3c0a4-52a1-4d0e-8b57-2f7e0c9d4a61"]
+pub struct SpawnTable {
+    pub enemy_budget: Budget,
+    pub item_budget: Budget,
+    /// Chance for a pick to be made as if the level was deeper.
+    pub out_of_depth_chance: f64,
+    /// How much deeper.
+    pub out_of_depth: u32,
+    pub enemies: Vec<SpawnEntry>,
+    pub items: Vec<SpawnEntry>,
+}
+
+impl RonAsset for SpawnTable {
+    const EXTENSIONS: &'static [&'static str] = &["spawns.ron"];
+
+    fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.out_of_depth_chance) {
+            return Err(format!(
+                "out_of_depth_chance is {}, it has to be between 0 and 1",
+                self.out_of_depth_chance
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SpawnTableHandle(Handle<SpawnTable>);
+
+/// Something that was placed while populating the level.
+#[derive(Debug, Clone)]
+pub struct Placed {
+    pub name: String,
+    pub pos: TilePos,
+    /// Picked as if the level was deeper.
+    pub out_of_depth: bool,
+}
+
+/// What was spent and placed when the level was populated. Missing until it has been.
+#[derive(Debug, Default)]
+pub struct SpawnReport {
+    pub enemy_budget: u32,
+    pub enemy_spent: u32,
+    pub item_budget: u32,
+    pub item_spent: u32,
+    pub enemies: Vec<Placed>,
+    pub items: Vec<Placed>,
+}
+
+/// Prints the spawn budget of the level and what was placed
+#[derive(ConsoleCommand)]
+#[console_command(name = "spawns")]
+struct SpawnsCommand;
+
+pub struct PopulatePlugin;
+
+impl Plugin for PopulatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SpawnTable>()
+            .init_asset_loader::<RonLoader<SpawnTable>>()
+            .init_resource::<CurrentLevel>()
+            .add_startup_system(load_spawn_table)
+            .add_enter_system(GameState::GeneratingMap, new_level)
+            .add_system(populate_level.run_not_in_state(GameState::GeneratingMap))
+            .add_console_command::<SpawnsCommand, _, _>(spawns_command);
+    }
+}
+
+fn load_spawn_table(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SpawnTableHandle(
+        asset_server.load("spawn_table.spawns.ron"),
+    ));
+}
+
+fn new_level(mut commands: Commands, mut level: ResMut<CurrentLevel>) {
+    level.theme = *Theme::ALL.choose(&mut rand::thread_rng()).unwrap();
+    commands.remove_resource::<SpawnReport>();
+
+    info!("Entering depth {} ({:?})", level.depth, level.theme);
+}
+
+/// Spends the budgets of the level on groups from the spawn table, once it has loaded.
+/// Groups are placed together in a random room, never where they can be seen from anywhere in the starting room.
+#[allow(clippy::too_many_arguments)]
+fn populate_level(
+    mut commands: Commands,
+    report: Option<Res<SpawnReport>>,
+    table: Res<SpawnTableHandle>,
+    tables: Res<Assets<SpawnTable>>,
+    level: Res<CurrentLevel>,
+    rooms: Query<(&Room, Option<&StartingRoom>)>,
+    floors: Query<(), With<Floor>>,
+    occupied: Query<&PassiveTilePos>,
+    mut map: MapQuery,
+    wall_q: Query<(Entity, &mut Tile), With<Wall>>,
+    mut enemies: EventWriter<SpawnEnemy>,
+    mut items: EventWriter<SpawnItem>,
+) {
+    if report.is_some() {
+        return;
+    }
+
+    let table = match tables.get(&table.0) {
+        Some(table) => table,
+        None => return,
+    };
+
+    let mut hidden = HashSet::new();
+    for (room, start) in rooms.iter() {
+        if start.is_none() {
+            continue;
+        }
+
+        // The player can be anywhere in the room by the time anything acts, so every floor tile of it is looked from.
+        let mut fov = FieldOfView::new(START_VIEW_RANGE);
+        for x in room.x1..=room.x2 {
+            for y in room.y1..=room.y2 {
+                let pos = TilePos(x, y);
+                hidden.insert(pos);
+
+                let is_floor = map
+                    .get_tile_entity(pos, 0, 0)
+                    .map_or(false, |tile| floors.contains(tile));
+                if is_floor {
+                    update_visible(&mut map, pos, &mut fov, &wall_q);
+                    hidden.extend(fov.tiles.drain(..));
+                }
+            }
+        }
+    }
+
+    // Free floor in every room, per room so that groups stay together.
+    let taken = occupied.iter().map(|pos| **pos).collect::<HashSet<_>>();
+    let mut free = rooms
+        .iter()
+        .filter(|(_, start)| start.is_none())
+        .map(|(room, _)| {
+            let mut tiles = vec![];
+            for x in room.x1..=room.x2 {
+                for y in room.y1..=room.y2 {
+                    let pos = TilePos(x, y);
+                    let is_floor = map
+                        .get_tile_entity(pos, 0, 0)
+                        .map_or(false, |tile| floors.contains(tile));
+
+                    if is_floor && !hidden.contains(&pos) && !taken.contains(&pos) {
+                        tiles.push(pos);
+                    }
+                }
+            }
+            tiles
+        })
+        .filter(|tiles| !tiles.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut report = SpawnReport {
+        enemy_budget: table.enemy_budget.at(level.depth),
+        item_budget: table.item_budget.at(level.depth),
+        ..Default::default()
+    };
+
+    let (enemy_budget, item_budget) = (report.enemy_budget, report.item_budget);
+    report.enemy_spent = spend(
+        table,
+        &table.enemies,
+        enemy_budget,
+        &level,
+        &mut free,
+        &mut report.enemies,
+    );
+    report.item_spent = spend(
+        table,
+        &table.items,
+        item_budget,
+        &level,
+        &mut free,
+        &mut report.items,
+    );
+
+    for Placed { name, pos, .. } in &report.enemies {
+        enemies.send(SpawnEnemy {
+            name: name.clone(),
+            pos: *pos,
+            ally: false,
+        });
+    }
+
+    for Placed { name, pos, .. } in &report.items {
+        items.send(SpawnItem {
+            name: name.clone(),
+            pos: *pos,
+        });
+    }
+
+    info!(
+        "Placed {} enemies and {} items",
+        report.enemies.len(),
+        report.items.len()
+    );
+
+    commands.insert_resource(report);
+}
+
+/// Picks groups from the entries until the budget is spent, taking the tiles they are placed on from `free`.
+/// Returns how much was spent.
+fn spend(
+    table: &SpawnTable,
+    entries: &[SpawnEntry],
+    budget: u32,
+    level: &CurrentLevel,
+    free: &mut [Vec<TilePos>],
+    placed: &mut Vec<Placed>,
+) -> u32 {
+    let mut rng = rand::thread_rng();
+    let mut remaining = budget;
+    let mut failed = 0;
+
+    while remaining > 0 && failed < MAX_FAILED_PICKS {
+        let out_of_depth = rng.gen_bool(table.out_of_depth_chance);
+        let depth = if out_of_depth {
+            level.depth + table.out_of_depth
+        } else {
+            level.depth
+        };
+
+        let allowed = entries
+            .iter()
+            .filter(|entry| entry.allowed(depth, level.theme) && entry.cost <= remaining)
+            .collect::<Vec<_>>();
+
+        let entry = match allowed.choose_weighted(&mut rng, |entry| entry.weight) {
+            Ok(entry) => *entry,
+            // Out of depth picks may have nothing cheap enough, normal ones can still be tried.
+            Err(_) if out_of_depth => {
+                failed += 1;
+                continue;
+            }
+            Err(_) => break,
+        };
+
+        let size = rng.gen_range(entry.group.0..=entry.group.1.max(entry.group.0)) as usize;
+        let room = match free
+            .iter_mut()
+            .filter(|tiles| tiles.len() >= size)
+            .choose(&mut rng)
+        {
+            Some(room) => room,
+            None => {
+                failed += 1;
+                continue;
+            }
+        };
+
+        room.shuffle(&mut rng);
+        for pos in room.drain(..size) {
+            placed.push(Placed {
+                name: entry.name.clone(),
+                pos,
+                out_of_depth,
+            });
+        }
+
+        remaining -= entry.cost;
+    }
+
+    budget - remaining
+}
+
+fn spawns_command(
+    mut spawns: ConsoleCommand<SpawnsCommand>,
+    report: Option<Res<SpawnReport>>,
+    level: Res<CurrentLevel>,
+) {
+    if spawns.take().is_some() {
+        let report = match report {
+            Some(report) => report,
+            None => {
+                reply_failed!(spawns, "The level has not been populated yet");
+                return;
+            }
+        };
+
+        reply!(spawns, "Depth {} ({:?})", level.depth, level.theme);
+        reply!(
+            spawns,
+            "Enemies: spent {} of {}",
+            report.enemy_spent,
+            report.enemy_budget
+        );
+        for placed in &report.enemies {
+            reply!(spawns, "  {}", describe(placed));
+        }
+
+        reply!(
+            spawns,
+            "Items: spent {} of {}",
+            report.item_spent,
+            report.item_budget
+        );
+        for placed in &report.items {
+            reply!(spawns, "  {}", describe(placed));
+        }
+
+        spawns.ok();
+    }
+}
+
+fn describe(placed: &Placed) -> String {
+    let mut line = format!("{} at ({}, {})", placed.name, placed.pos.0, placed.pos.1);
+    if placed.out_of_depth {
+        line.push_str(", out of depth");
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = include_str!("../../assets/spawn_table.spawns.ron");
+
+    #[test]
+    fn shipped_spawn_table_is_valid() {
+        let table = ron::de::from_str::<SpawnTable>(TABLE).unwrap();
+
+        assert_eq!(table.validate(), Ok(()));
+    }
+
+    #[test]
+    fn out_of_depth_chance_outside_zero_to_one_is_rejected() {
+        for chance in ["1.5", "-0.1"] {
+            let ron = TABLE.replace(
+                "out_of_depth_chance: 0.05",
+                &format!("out_of_depth_chance: {chance}"),
+            );
+            let table = ron::de::from_str::<SpawnTable>(&ron).unwrap();
+
+            assert!(table.validate().is_err(), "{chance} was accepted");
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Changing what tiles are made of while the level is played.
+//! Every change goes through [`AlterTerrain`], which swaps the texture and the [`Wall`]/[`Floor`] components of the tile
+//! together, and recomputes the fields of view that could be affected right away.
+//! Wall colliders are rebuilt from [`TerrainAltered`] in the same frame, and Dijkstra maps pick up the new components by themselves.
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::{MapQuery, Tile, TilePos};
+use iyes_loopless::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    components::{PassiveTilePos, Player},
+    core::{distance, world_ticked, SpellLabel, TurnLabel, TurnState},
+    ActiveState, GameState,
+};
+
+use super::{
+    update_visible_by, Explored, FieldOfView, Floor, FovCalculationLabel, Pit, TilePaint, Wall,
+    Water,
+};
+
+/// What a tile is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Terrain {
+    Floor,
+    Wall,
+    Pit,
+    Water,
+}
+
+impl Terrain {
+    /// Index of the tile in `tiles/tiles.png`.
+    pub fn texture(&self) -> u16 {
+        match self {
+            Terrain::Floor => 6,
+            Terrain::Wall => 10,
+            Terrain::Pit => 78,
+            // There is no water tile yet, this dark one stands in for it.
+            Terrain::Water => 40,
+        }
+    }
+}
+
+/// Turns a tile into another kind of terrain, for some turns or for good.
+#[derive(Debug, Clone, Copy)]
+pub struct AlterTerrain {
+    pub pos: TilePos,
+    pub terrain: Terrain,
+    /// Turns before it goes back to what it was, it stays if none.
+    pub turns: Option<u32>,
+}
+
+/// A tile was turned into another kind of terrain.
+#[derive(Debug, Clone, Copy)]
+pub struct TerrainAltered {
+    pub pos: TilePos,
+    pub terrain: Terrain,
+}
+
+/// Terrain that goes back to what it was after a while.
+#[derive(Debug, Component)]
+pub struct TemporaryTerrain {
+    pub turns: u32,
+    pub revert: Terrain,
+}
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub struct TerrainLabel;
+
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AlterTerrain>()
+            .add_event::<TerrainAltered>()
+            .add_system(
+                alter_terrain
+                    .run_not_in_state(GameState::GeneratingMap)
+                    .label(TerrainLabel)
+                    .after(SpellLabel)
+                    .before(FovCalculationLabel),
+            )
+            .add_enter_system(TurnState::Player, revert_temporary_terrain)
+            .add_system(
+                revert_temporary_terrain
+                    .run_in_state(ActiveState::Playing)
+                    .run_in_state(GameState::FreeRoam)
+                    .run_if(world_ticked)
+                    .after(TurnLabel::Input),
+            );
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn alter_terrain(
+    mut commands: Commands,
+    mut events: EventReader<AlterTerrain>,
+    mut altered: EventWriter<TerrainAltered>,
+    mut map: MapQuery,
+    mut tiles: Query<&mut Tile>,
+    terrain: Query<(Option<&Wall>, Option<&Pit>, Option<&Water>)>,
+    mut fovs: Query<(&PassiveTilePos, &mut FieldOfView, Option<&Player>)>,
+    mut paints: Query<&mut TilePaint>,
+) {
+    // The new components are only added at the end of the frame, sight goes by this until then.
+    let mut blocking = HashMap::new();
+    let mut changed = vec![];
+
+    for AlterTerrain {
+        pos,
+        terrain: to,
+        turns,
+    } in events.iter()
+    {
+        let entity = match map.get_tile_entity(*pos, 0, 0) {
+            Ok(entity) => entity,
+            Err(_) => continue,
+        };
+
+        let was = match terrain.get(entity) {
+            Ok((Some(_), _, _)) => Terrain::Wall,
+            Ok((_, Some(_), _)) => Terrain::Pit,
+            Ok((_, _, Some(_))) => Terrain::Water,
+            _ => Terrain::Floor,
+        };
+
+        if let Ok(mut tile) = tiles.get_mut(entity) {
+            tile.texture_index = to.texture();
+        }
+        map.notify_chunk_for_tile(*pos, 0u16, 0u16);
+
+        let mut tile = commands.entity(entity);
+        tile.remove::<Wall>()
+            .remove::<Floor>()
+            .remove::<Pit>()
+            .remove::<Water>()
+            .remove::<TemporaryTerrain>();
+
+        match to {
+            Terrain::Floor => tile.insert(Floor),
+            Terrain::Wall => tile.insert(Wall),
+            Terrain::Pit => tile.insert(Pit),
+            Terrain::Water => tile.insert(Water),
+        };
+
+        if let Some(turns) = turns {
+            tile.insert(TemporaryTerrain {
+                turns: *turns,
+                revert: was,
+            });
+        }
+
+        debug!("{pos:?} went from {was:?} to {to:?}");
+
+        blocking.insert(entity, *to == Terrain::Wall);
+        changed.push(*pos);
+        altered.send(TerrainAltered {
+            pos: *pos,
+            terrain: *to,
+        });
+    }
+
+    if changed.is_empty() {
+        return;
+    }
+
+    // Only those close enough to have the change in view can see any differently.
+    for (pos, mut fov, player) in fovs.iter_mut() {
+        if !changed
+            .iter()
+            .any(|tile| distance(**pos, *tile) <= fov.range)
+        {
+            continue;
+        }
+
+        update_visible_by(&mut map, **pos, &mut fov, |tile| {
+            blocking.get(&tile).copied().unwrap_or_else(|| {
+                terrain
+                    .get(tile)
+                    .map_or(false, |(wall, _, _)| wall.is_some())
+            })
+        });
+
+        if player.is_none() {
+            continue;
+        }
+
+        for tile in &fov.tiles {
+            if let Ok(entity) = map.get_tile_entity(*tile, 0, 0) {
+                if let Ok(mut paint) = paints.get_mut(entity) {
+                    *paint = paint.greater_of(TilePaint::Visible);
+                }
+                commands.entity(entity).insert(Explored);
+            }
+        }
+    }
+}
+
+/// Counts down temporary terrain once a round, and turns it back once it runs out.
+fn revert_temporary_terrain(
+    mut tiles: Query<(&TilePos, &mut TemporaryTerrain)>,
+    mut writer: EventWriter<AlterTerrain>,
+) {
+    for (pos, mut temporary) in tiles.iter_mut() {
+        temporary.turns = temporary.turns.saturating_sub(1);
+
+        if temporary.turns == 0 {
+            writer.send(AlterTerrain {
+                pos: *pos,
+                terrain: temporary.revert,
+                turns: None,
+            });
+        }
+    }
+}
@@ -1,7 +1,15 @@
 use std::cmp::Ordering;
 
 /// Various Tile related structures
-use bevy::prelude::{Color, Component};
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{Color, Component, Entity, Query, With},
+};
+use bevy_ecs_tilemap::{MapQuery, TilePos};
+
+use crate::util::tile_ray;
+
+use super::Terrain;
 
 #[derive(Debug, Component)]
 pub struct Wall;
@@ -9,11 +17,110 @@ pub struct Wall;
 #[derive(Debug, Component)]
 pub struct Floor;
 
+/// A hole in the floor, which can be seen across but not walked over.
+#[derive(Debug, Component)]
+pub struct Pit;
+
+/// Too deep to wade through, until it is frozen.
+#[derive(Debug, Component)]
+pub struct Water;
+
+/// The player has seen this tile at some point.
+#[derive(Debug, Component)]
+pub struct Explored;
+
+/// A way off the current level.
+#[derive(Debug, Component)]
+pub struct Exit;
+
+/// A door, which blocks movement while closed.
+#[derive(Debug, Component)]
+pub struct Door {
+    pub open: bool,
+}
+
+/// Looks up what is on the tiles of the map.
+#[derive(SystemParam)]
+pub struct MapTiles<'w, 's> {
+    map: MapQuery<'w, 's>,
+    walls: Query<'w, 's, (), With<Wall>>,
+    pits: Query<'w, 's, (), With<Pit>>,
+    water: Query<'w, 's, (), With<Water>>,
+    doors: Query<'w, 's, &'static Door>,
+    explored: Query<'w, 's, (), With<Explored>>,
+}
+
+impl<'w, 's> MapTiles<'w, 's> {
+    /// The tile entity at a position, if the position is on the map.
+    pub fn entity(&mut self, pos: TilePos) -> Option<Entity> {
+        self.map.get_tile_entity(pos, 0, 0).ok()
+    }
+
+    pub fn is_wall(&mut self, pos: TilePos) -> bool {
+        self.entity(pos)
+            .map_or(false, |tile| self.walls.contains(tile))
+    }
+
+    /// What the tile is made of, if the position is on the map.
+    pub fn terrain(&mut self, pos: TilePos) -> Option<Terrain> {
+        let tile = self.entity(pos)?;
+
+        Some(if self.walls.contains(tile) {
+            Terrain::Wall
+        } else if self.pits.contains(tile) {
+            Terrain::Pit
+        } else if self.water.contains(tile) {
+            Terrain::Water
+        } else {
+            Terrain::Floor
+        })
+    }
+
+    pub fn is_explored(&mut self, pos: TilePos) -> bool {
+        self.entity(pos)
+            .map_or(false, |tile| self.explored.contains(tile))
+    }
+
+    /// Whether the tile is on the map and nothing about the tile itself stops an actor from standing on it.
+    pub fn is_walkable(&mut self, pos: TilePos) -> bool {
+        match self.entity(pos) {
+            Some(tile) => {
+                self.terrain(pos) == Some(Terrain::Floor)
+                    && self.doors.get(tile).map_or(true, |door| door.open)
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the tile can't be seen through. Walls and closed doors block sight, pits and water don't.
+    pub fn blocks_sight(&mut self, pos: TilePos) -> bool {
+        match self.entity(pos) {
+            Some(tile) => {
+                self.walls.contains(tile) || self.doors.get(tile).map_or(false, |door| !door.open)
+            }
+            None => true,
+        }
+    }
+
+    /// Whether nothing blocks a straight line between two tiles. Walls and closed doors block it, except at the ends.
+    pub fn in_sight(&mut self, from: TilePos, to: TilePos) -> bool {
+        let steps = (from.0 as i32 - to.0 as i32)
+            .unsigned_abs()
+            .max((from.1 as i32 - to.1 as i32).unsigned_abs());
+
+        tile_ray(from, to, steps)
+            .into_iter()
+            .all(|pos| pos == to || !self.blocks_sight(pos))
+    }
+}
+
 #[derive(PartialEq, Component, Copy, Clone)]
 /// How to paint a certain tile this frame.
 pub enum TilePaint {
     /// This tile is being painted with a color to indicate that the player is hovering over it.
     CursorDraw(Color),
+    /// This tile is being painted with a color by a debug overlay, whether it has been seen or not.
+    Debug(Color),
     /// This tile is visible. Color is white.
     Visible,
     /// This tile has previously been seen.
@@ -0,0 +1,74 @@
+//! Marks above enemies showing how aware they are of the player.
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    core::{Awareness, AwarenessLevel},
+    GameState,
+};
+
+/// How far above the center of an enemy the mark is drawn.
+const MARK_OFFSET: f32 = 12.;
+
+/// The mark above an enemy.
+#[derive(Debug, Component)]
+pub struct AwarenessMark;
+
+pub struct AwarenessUiPlugin;
+
+impl Plugin for AwarenessUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(add_awareness_marks.run_not_in_state(GameState::GeneratingMap))
+            .add_system(update_awareness_marks.run_not_in_state(GameState::GeneratingMap));
+    }
+}
+
+fn add_awareness_marks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    added: Query<Entity, Added<Awareness>>,
+) {
+    for entity in added.iter() {
+        let mark = commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/PublicPixel.ttf"),
+                        font_size: 8.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                transform: Transform::from_xyz(0., MARK_OFFSET, 1.),
+                ..Default::default()
+            })
+            .insert(AwarenessMark)
+            .id();
+
+        commands.entity(entity).add_child(mark);
+    }
+}
+
+fn update_awareness_marks(
+    enemies: Query<(&Awareness, &Children), Changed<Awareness>>,
+    mut marks: Query<&mut Text, With<AwarenessMark>>,
+) {
+    for (awareness, children) in enemies.iter() {
+        let (value, color) = match awareness.level() {
+            AwarenessLevel::Unaware => ("", Color::WHITE),
+            AwarenessLevel::Suspicious => ("?", Color::YELLOW),
+            AwarenessLevel::Alert => ("!", Color::RED),
+        };
+
+        for child in children.iter() {
+            if let Ok(mut text) = marks.get_mut(*child) {
+                text.sections[0].value = value.to_string();
+                text.sections[0].style.color = color;
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! The hand of spell cards along the bottom of the screen.
+//! Cards slide in when drawn, and rise while hovered or readied. Clicking a card readies it.
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_easings::{Ease, EaseFunction, EasingComponent, EasingType};
+
+use crate::{
+    core::{Deck, SpellDef, SpellDefs},
+    util::PlayerQuery,
+};
+
+const CARD_WIDTH: f32 = 64.;
+const CARD_HEIGHT: f32 = 96.;
+/// How far a hovered or readied card rises.
+const RAISE: f32 = 24.;
+const EASE_DURATION: Duration = Duration::from_millis(400);
+
+/// The row the cards are laid out in.
+#[derive(Debug, Component)]
+pub struct HandUi;
+
+/// A card in the hand.
+#[derive(Debug, Component)]
+pub struct CardUi {
+    /// Index in the hand.
+    index: usize,
+    raised: bool,
+}
+
+pub struct CardUiPlugin;
+
+impl Plugin for CardUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_hand_ui)
+            .add_system(update_hand)
+            .add_system(animate_cards.after(update_hand));
+    }
+}
+
+/// Where a card sits, `offset` pixels above its place in the row.
+fn card_style(offset: f32) -> Style {
+    Style {
+        size: Size::new(Val::Px(CARD_WIDTH), Val::Px(CARD_HEIGHT)),
+        margin: Rect {
+            left: Val::Px(4.),
+            right: Val::Px(4.),
+            ..Default::default()
+        },
+        position: Rect {
+            bottom: Val::Px(offset),
+            ..Default::default()
+        },
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::FlexEnd,
+        ..Default::default()
+    }
+}
+
+fn ease_card(commands: &mut Commands, entity: Entity, style: &Style, offset: f32) {
+    commands
+        .entity(entity)
+        .remove::<EasingComponent<Style>>()
+        .insert(style.clone().ease_to(
+            card_style(offset),
+            EaseFunction::ExponentialOut,
+            EasingType::Once {
+                duration: EASE_DURATION,
+            },
+        ));
+}
+
+fn spawn_hand_ui(mut commands: Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Px(CARD_HEIGHT)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(5.),
+                    ..Default::default()
+                },
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(HandUi);
+}
+
+/// Lays the hand out again whenever cards are drawn or played.
+fn update_hand(
+    mut commands: Commands,
+    deck: PlayerQuery<&Deck>,
+    hand_ui: Query<(Entity, Option<&Children>), With<HandUi>>,
+    defs: Res<SpellDefs>,
+    assets: Res<Assets<SpellDef>>,
+    asset_server: Res<AssetServer>,
+    mut shown: Local<Vec<String>>,
+) {
+    let deck = match deck.get_single() {
+        Ok(deck) => deck,
+        Err(_) => return,
+    };
+
+    if *shown == deck.hand {
+        return;
+    }
+    *shown = deck.hand.clone();
+
+    let (row, children) = hand_ui.single();
+    for child in children.iter().flat_map(|children| children.iter()) {
+        commands.entity(*child).despawn_recursive();
+    }
+
+    let font = asset_server.load("fonts/PublicPixel.ttf");
+
+    for (index, name) in deck.hand.iter().enumerate() {
+        let icon = defs
+            .find(name, &assets)
+            .and_then(|(_, def)| def.icon.clone());
+
+        // Drawn cards slide up from below the screen.
+        let style = card_style(-CARD_HEIGHT);
+
+        let card = commands
+            .spawn_bundle(ButtonBundle {
+                style: style.clone(),
+                image: icon
+                    .as_ref()
+                    .map(|icon| UiImage::from(asset_server.load(icon.as_str())))
+                    .unwrap_or_default(),
+                color: match icon {
+                    Some(_) => Color::WHITE.into(),
+                    None => Color::rgb(0.2, 0.2, 0.3).into(),
+                },
+                ..Default::default()
+            })
+            .insert(CardUi {
+                index,
+                raised: false,
+            })
+            .with_children(|card| {
+                if icon.is_none() {
+                    card.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            name,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 8.0,
+                                color: Color::WHITE,
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    });
+                }
+            })
+            .id();
+
+        ease_card(&mut commands, card, &style, 0.);
+        commands.entity(row).add_child(card);
+    }
+}
+
+/// Raises hovered and readied cards, and readies clicked ones.
+fn animate_cards(
+    mut commands: Commands,
+    mut deck: PlayerQuery<&mut Deck>,
+    mut cards: Query<(Entity, &mut CardUi, &Interaction, &Style)>,
+) {
+    let mut deck = match deck.get_single_mut() {
+        Ok(deck) => deck,
+        Err(_) => return,
+    };
+
+    for (entity, mut card, interaction, style) in cards.iter_mut() {
+        if *interaction == Interaction::Clicked && deck.readied != Some(card.index) {
+            deck.readied = Some(card.index);
+        }
+
+        let raised = *interaction != Interaction::None || deck.readied == Some(card.index);
+        if raised != card.raised {
+            card.raised = raised;
+            ease_card(
+                &mut commands,
+                entity,
+                style,
+                if raised { RAISE } else { 0. },
+            );
+        }
+    }
+}
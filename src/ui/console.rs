@@ -5,11 +5,16 @@ use bevy_console::{
     reply, reply_failed, AddConsoleCommand, CommandArgs, CommandHelp, CommandInfo, CommandName,
     ConsoleCommand, ConsoleConfiguration, ConsoleOpen, PrintConsoleLine, ToggleConsoleKey,
 };
-use leafwing_input_manager::prelude::InputMap;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::{prelude::InputMap, Actionlike};
 use std::{iter::empty, str::FromStr};
 use strum::EnumString;
 
-use crate::{components::Player, core::MovementAction};
+use crate::{
+    components::Player,
+    core::{CommandAction, Hotbar, HotbarAction, HotbarSlot, MovementAction, SpellDef, SpellDefs},
+    GameState,
+};
 // debatable that this should be in ui
 
 /// The plugin representing the Console UI element
@@ -34,11 +39,14 @@ impl Plugin for ConsolePlugin {
         app.add_console_command::<BindCommand, _, _>(bind_command);
         app.add_console_command::<LogCommand, _, _>(log_command);
         app.add_console_command::<CloseCommand, _, _>(close_command);
+        app.add_console_command::<TurnBasedCommand, _, _>(turn_based_command);
     }
 }
 static WELCOME_MESSAGE: &str = r#"
 Welcome to game.
 This console is your pause screen. Press F1/esc/` to open/close the console.
+Type a number before waiting (.), searching (x) or moving to repeat it that many times, 5 then x searches five times.
+Shift and a number uses that slot of the hotbar.
 Here is a list of available commands. Type help to see it again, or help <command> for information on a specific command. 
 "#;
 
@@ -67,28 +75,59 @@ fn welcome(mut writer: EventWriter<PrintConsoleLine>, config: Res<ConsoleConfigu
     writer.send(PrintConsoleLine { line: "".into() });
 }
 
-/// Rebinds a key
+/// Rebinds a key, or what a hotbar slot uses
 #[derive(ConsoleCommand)]
 #[console_command(name = "bind")]
 struct BindCommand {
-    /// Key to press
+    /// Key to press, or a hotbar slot like slot1
     key: String,
-    /// Action that is taken
+    /// Action that is taken, or for a slot a spell, card<n>, item:<name> or empty
     action: String,
 }
 
+#[allow(clippy::type_complexity)]
 fn bind_command(
     mut bind: ConsoleCommand<BindCommand>,
-    mut input_query: Query<&mut InputMap<MovementAction>, With<Player>>,
+    mut input_query: Query<
+        (
+            &mut InputMap<MovementAction>,
+            &mut InputMap<CommandAction>,
+            &mut InputMap<HotbarAction>,
+            &mut Hotbar,
+        ),
+        With<Player>,
+    >,
+    defs: Res<SpellDefs>,
+    assets: Res<Assets<SpellDef>>,
 ) {
     if let Some(BindCommand { key, action }) = bind.take() {
-        let action = match MovementAction::from_str(&action) {
-            Ok(action) => action,
-            Err(_) => {
-                reply_failed!(bind, "No such action: '{action}'");
-                return;
+        let (mut movement_input, mut command_input, mut hotbar_input, mut hotbar) =
+            input_query.single_mut();
+
+        if let Ok(slot) = HotbarAction::from_str(&key) {
+            let contents = HotbarSlot::parse(&action);
+
+            if let HotbarSlot::Spell(name) = &contents {
+                if defs.find(name, &assets).is_none() {
+                    reply_failed!(bind, "No spell named '{name}'");
+                    return;
+                }
             }
-        };
+
+            reply!(bind, "{slot:?} now uses {contents:?}");
+            hotbar[slot.slot()] = contents;
+            bind.ok();
+            return;
+        }
+
+        let movement = MovementAction::from_str(&action).ok();
+        let command = CommandAction::from_str(&action).ok();
+        let hotbar_action = HotbarAction::from_str(&action).ok();
+
+        if movement.is_none() && command.is_none() && hotbar_action.is_none() {
+            reply_failed!(bind, "No such action: '{action}'");
+            return;
+        }
 
         let key: KeyCode = match CustomBindInput::from_str(&key) {
             Ok(key) => key.into(),
@@ -98,22 +137,35 @@ fn bind_command(
             }
         };
 
-        let mut input = input_query.single_mut();
-        let conflict_action = input.iter().find_map(|(inputs, action)| {
-            if inputs.contains(&key.into()) {
-                return Some(action);
-            }
-            None
-        });
-        if let Some(action) = conflict_action {
-            input.remove(action, key);
+        // A key only does one thing, so it is taken from whatever it was bound to before.
+        unbind(&mut movement_input, key);
+        unbind(&mut command_input, key);
+        unbind(&mut hotbar_input, key);
+
+        if let Some(action) = movement {
+            movement_input.insert(key, action);
+        } else if let Some(action) = command {
+            command_input.insert(key, action);
+        } else if let Some(action) = hotbar_action {
+            hotbar_input.insert(key, action);
         }
 
-        input.insert(key, action);
         bind.ok();
     }
 }
 
+fn unbind<A: Actionlike>(input: &mut InputMap<A>, key: KeyCode) {
+    let conflict_action = input.iter().find_map(|(inputs, action)| {
+        if inputs.contains(&key.into()) {
+            return Some(action);
+        }
+        None
+    });
+    if let Some(action) = conflict_action {
+        input.remove(action, key);
+    }
+}
+
 /// Prints given arguments to the console
 #[derive(ConsoleCommand)]
 #[console_command(name = "log")]
@@ -145,6 +197,31 @@ fn close_command(mut close: ConsoleCommand<CloseCommand>, mut console_open: ResM
     }
 }
 
+/// Switches between free roam and turn based play
+#[derive(ConsoleCommand)]
+#[console_command(name = "turnbased")]
+struct TurnBasedCommand;
+
+fn turn_based_command(
+    mut turn_based: ConsoleCommand<TurnBasedCommand>,
+    mut commands: Commands,
+    state: Res<CurrentState<GameState>>,
+) {
+    if turn_based.take().is_some() {
+        let next = match state.0 {
+            GameState::FreeRoam => GameState::TurnBased,
+            GameState::TurnBased => GameState::FreeRoam,
+            GameState::GeneratingMap => {
+                reply_failed!(turn_based, "The map is still being generated");
+                return;
+            }
+        };
+
+        reply!(turn_based, "{:?} -> {next:?}", state.0);
+        commands.insert_resource(NextState(next));
+    }
+}
+
 pub trait AddConvar {
     // fn add_command<C: CommandHelp + CommandName + 'static, Params>(
     //     &mut self,
@@ -0,0 +1,161 @@
+//! The hotbar in the bottom right corner, showing what every slot uses and how long until it can be used again.
+use bevy::prelude::*;
+
+use crate::{
+    components::{Inventory, Item},
+    core::{Cooldown, Deck, Hotbar, HotbarSlot, SpellCharges, SpellDef, SpellDefs, SCROLL_PREFIX},
+    util::PlayerQuery,
+};
+
+const SLOT_SIZE: f32 = 32.;
+const SLOT_COLOR: Color = Color::rgb(0.2, 0.2, 0.3);
+/// Slots that can't be used right now are darkened.
+const UNUSABLE_COLOR: Color = Color::rgb(0.35, 0.35, 0.35);
+
+/// A slot of the hotbar. Its children are the label and the counter.
+#[derive(Debug, Component)]
+pub struct HotbarSlotUi(usize);
+
+pub struct HotbarUiPlugin;
+
+impl Plugin for HotbarUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_hotbar_ui)
+            .add_system(update_hotbar_ui);
+    }
+}
+
+fn spawn_hotbar_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/PublicPixel.ttf");
+    let text = |size: f32| TextBundle {
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: size,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(5.),
+                    right: Val::Percent(2.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .with_children(|bar| {
+            for index in 0..Hotbar::default().len() {
+                bar.spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(SLOT_SIZE), Val::Px(SLOT_SIZE)),
+                        margin: Rect::all(Val::Px(2.)),
+                        flex_direction: FlexDirection::ColumnReverse,
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..Default::default()
+                    },
+                    color: SLOT_COLOR.into(),
+                    ..Default::default()
+                })
+                .insert(HotbarSlotUi(index))
+                .with_children(|slot| {
+                    slot.spawn_bundle(text(6.));
+                    slot.spawn_bundle(text(8.));
+                });
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn update_hotbar_ui(
+    player: PlayerQuery<(&Hotbar, &Deck, &Inventory, Option<&SpellCharges>)>,
+    items: Query<&Item>,
+    mut slots: Query<(&HotbarSlotUi, &Children, &mut UiImage, &mut UiColor)>,
+    mut texts: Query<&mut Text>,
+    defs: Res<SpellDefs>,
+    assets: Res<Assets<SpellDef>>,
+    asset_server: Res<AssetServer>,
+) {
+    let (hotbar, deck, inventory, charges) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    for (slot, children, mut image, mut color) in slots.iter_mut() {
+        // The spell the slot casts, whether it can be used at all, and the count of items.
+        let (spell, usable, carried) = match &hotbar[slot.0] {
+            HotbarSlot::Empty => (None, false, None),
+            HotbarSlot::Card(index) => (deck.hand.get(*index).cloned(), true, None),
+            HotbarSlot::Spell(name) => (Some(name.clone()), deck.hand.contains(name), None),
+            HotbarSlot::Item(name) => {
+                let count = inventory
+                    .iter()
+                    .filter(|entity| items.get(**entity).map_or(false, |item| item.name == *name))
+                    .count();
+                let spell = name.strip_prefix(SCROLL_PREFIX).map(str::to_string);
+
+                (spell.or_else(|| Some(name.clone())), count > 0, Some(count))
+            }
+        };
+
+        let def = spell
+            .as_ref()
+            .and_then(|name| defs.find(name, &assets))
+            .map(|(_, def)| def);
+
+        let icon = def.and_then(|def| def.icon.as_ref());
+        let new_image: UiImage = match icon {
+            Some(icon) => asset_server.load(icon.as_str()).into(),
+            None => Default::default(),
+        };
+        if image.0 != new_image.0 {
+            *image = new_image;
+        }
+
+        let label = match icon {
+            Some(_) => String::new(),
+            None => spell.clone().unwrap_or_default(),
+        };
+
+        // Items are free to use, spells show how long until they are back, or how many charges are left.
+        let recharging = carried.is_none()
+            && matches!((def, charges), (Some(def), Some(charges)) if charges.available(def) == 0);
+
+        let counter = match (def, charges, carried) {
+            (_, _, Some(count)) => Some(format!("x{count}")),
+            (Some(def), Some(charges), None) if recharging => {
+                charges.ready_in(def).map(|ready_in| match ready_in {
+                    Cooldown::Turns(turns) => turns.to_string(),
+                    Cooldown::Seconds(seconds) => format!("{seconds:.0}s"),
+                })
+            }
+            (Some(def), Some(charges), None) if def.charges > 1 => {
+                Some(charges.available(def).to_string())
+            }
+            _ => None,
+        };
+
+        *color = match (icon, usable && !recharging) {
+            (_, false) if spell.is_some() => UNUSABLE_COLOR.into(),
+            (Some(_), _) => Color::WHITE.into(),
+            _ => SLOT_COLOR.into(),
+        };
+
+        if let Ok(mut text) = texts.get_mut(children[0]) {
+            text.sections[0].value = label;
+        }
+        if let Ok(mut text) = texts.get_mut(children[1]) {
+            text.sections[0].value = counter.unwrap_or_default();
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! Various UI functions
 use crate::{
-    components::{Health, HealthText},
+    components::{Health, HealthText, Mana, ManaText},
     util::PlayerQuery,
     ActiveState, GameState,
 };
@@ -10,11 +10,10 @@ pub struct MiscPlugin;
 
 impl Plugin for MiscPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(
-            update_health
-                .run_in_state(ActiveState::Playing)
-                .run_not_in_state(GameState::GeneratingMap),
-        );
+        // Also runs after death, so that the killing blow shows.
+        app.add_system(update_health.run_not_in_state(GameState::GeneratingMap))
+            .add_system(update_mana.run_not_in_state(GameState::GeneratingMap))
+            .add_enter_system(ActiveState::GameOver, show_game_over);
     }
 }
 
@@ -24,5 +23,43 @@ fn update_health(
 ) {
     let player_health = health_query.single_mut();
     let mut text = text_query.single_mut();
-    text.sections[0].value = player_health.0.to_string();
+    text.sections[0].value = player_health.0.max(0).to_string();
+}
+
+fn update_mana(
+    mut mana_query: PlayerQuery<&Mana>,
+    mut text_query: Query<&mut Text, With<ManaText>>,
+) {
+    let player_mana = mana_query.single_mut();
+    let mut text = text_query.single_mut();
+    text.sections[0].value = player_mana.0.to_string();
+}
+
+fn show_game_over(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "You died",
+                    TextStyle {
+                        font: asset_server.load("fonts/PublicPixel.ttf"),
+                        font_size: 40.0,
+                        color: Color::MAROON,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
 }
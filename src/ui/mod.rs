@@ -1,8 +1,12 @@
 //! Modules for the game UI.
+mod awareness;
+mod cards;
 mod console;
 mod fps;
+mod hotbar;
 mod misc;
 mod plugin;
+mod spells;
 
 pub use console::*;
 pub use plugin::UiPlugin;
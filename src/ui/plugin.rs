@@ -1,9 +1,13 @@
 use bevy::prelude::{App, Plugin};
 
+use super::awareness::AwarenessUiPlugin;
+use super::cards::CardUiPlugin;
 use super::console::ConsolePlugin;
 // use super::console::ConsolePlugin;
 use super::fps::FPSPlugin;
+use super::hotbar::HotbarUiPlugin;
 use super::misc::MiscPlugin;
+use super::spells::SpellUiPlugin;
 
 /// Default UI Plugin, imports everything
 pub struct UiPlugin;
@@ -14,6 +18,10 @@ impl Plugin for UiPlugin {
             // .add_plugin(ConsolePlugin)
             .add_plugin(FPSPlugin)
             .add_plugin(MiscPlugin)
+            .add_plugin(AwarenessUiPlugin)
+            .add_plugin(SpellUiPlugin)
+            .add_plugin(CardUiPlugin)
+            .add_plugin(HotbarUiPlugin)
             .add_plugin(ConsolePlugin);
     }
 }
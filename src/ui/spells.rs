@@ -0,0 +1,70 @@
+//! Telling the player about their spells.
+use bevy::prelude::*;
+
+use crate::core::CastRefused;
+
+/// How long the reason a spell was refused stays on screen.
+const REFUSAL_SECONDS: f32 = 2.;
+
+/// The line telling why the last spell was refused.
+#[derive(Debug, Component)]
+pub struct RefusalText;
+
+pub struct SpellUiPlugin;
+
+impl Plugin for SpellUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_refusal_text)
+            .add_system(show_refusals);
+    }
+}
+
+fn spawn_refusal_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/PublicPixel.ttf"),
+                    font_size: 12.0,
+                    color: Color::ORANGE_RED,
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Percent(15.),
+                    left: Val::Percent(5.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(RefusalText);
+}
+
+fn show_refusals(
+    mut refusals: EventReader<CastRefused>,
+    mut text: Query<&mut Text, With<RefusalText>>,
+    time: Res<Time>,
+    mut shown_for: Local<Option<Timer>>,
+) {
+    let mut text = match text.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+
+    if let Some(refused) = refusals.iter().last() {
+        text.sections[0].value = format!("Can't cast {}, {}", refused.spell, refused.reason);
+        *shown_for = Some(Timer::from_seconds(REFUSAL_SECONDS, false));
+    }
+
+    if let Some(timer) = &mut *shown_for {
+        if timer.tick(time.delta()).just_finished() {
+            text.sections[0].value.clear();
+            *shown_for = None;
+        }
+    }
+}
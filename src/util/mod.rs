@@ -6,7 +6,7 @@ use std::{
 
 use bevy::{
     log::Level,
-    math::Vec2,
+    math::{IVec2, Vec2},
     prelude::{Plugin, SystemStage},
 };
 use bevy_ecs_tilemap::TilePos;
@@ -14,11 +14,13 @@ use bevy_ecs_tilemap::TilePos;
 mod consts;
 mod debug;
 mod queries;
+mod ron_asset;
 
 pub use consts::*;
 pub use debug::*;
 use iyes_loopless::prelude::FixedTimestepStage;
 pub use queries::*;
+pub use ron_asset::*;
 use tracing_subscriber::{
     filter::LevelFilter, prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt,
     EnvFilter, Layer, Registry,
@@ -40,6 +42,40 @@ pub fn trans_from_tile(pos: &TilePos) -> Vec2 {
     )
 }
 
+/// Offsets a tile position, returns None if the result would be outside of the map on the negative side.
+pub fn offset_tile(pos: &TilePos, offset: IVec2) -> Option<TilePos> {
+    let x = pos.0 as i32 + offset.x;
+    let y = pos.1 as i32 + offset.y;
+
+    (x >= 0 && y >= 0).then_some(TilePos(x as u32, y as u32))
+}
+
+/// The tiles on a straight line from one tile towards another, not including the first.
+/// The line carries on past the second tile until it is `length` tiles long, or leaves the map on the negative side.
+pub fn tile_ray(from: TilePos, towards: TilePos, length: u32) -> Vec<TilePos> {
+    let delta = IVec2::new(
+        towards.0 as i32 - from.0 as i32,
+        towards.1 as i32 - from.1 as i32,
+    );
+    let steps = delta.x.abs().max(delta.y.abs());
+
+    if steps == 0 {
+        return vec![];
+    }
+
+    (1..=length as i32)
+        .map_while(|step| {
+            let t = step as f32 / steps as f32;
+            let offset = IVec2::new(
+                (delta.x as f32 * t).round() as i32,
+                (delta.y as f32 * t).round() as i32,
+            );
+
+            offset_tile(&from, offset)
+        })
+        .collect()
+}
+
 pub struct LogPlugin;
 
 impl Plugin for LogPlugin {
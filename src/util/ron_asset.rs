@@ -0,0 +1,105 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{Asset, AssetLoader, LoadContext, LoadState, LoadedAsset},
+    log::error,
+    prelude::{AssetServer, Assets, Handle, HandleUntyped},
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+/// An asset read straight from a RON file.
+pub trait RonAsset: Asset + DeserializeOwned {
+    /// File extensions of the asset, e.g. `enemy.ron`.
+    const EXTENSIONS: &'static [&'static str];
+
+    /// Checks what the types alone can't, an asset that fails is not loaded.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Loads any [`RonAsset`].
+pub struct RonLoader<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for RonLoader<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: RonAsset> AssetLoader for RonLoader<T> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let asset = ron::de::from_bytes::<T>(bytes)?;
+            if let Err(err) = asset.validate() {
+                let path = load_context.path().display();
+                error!("{path} is invalid: {err}");
+                return Err(anyhow::Error::msg(err));
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        T::EXTENSIONS
+    }
+}
+
+/// A [`RonAsset`] that is looked up by name.
+pub trait NamedAsset: RonAsset {
+    fn name(&self) -> &str;
+}
+
+/// Handles to every asset in a folder, kept so that they stay loaded.
+#[derive(Debug)]
+pub struct AssetFolder<T> {
+    handles: Vec<HandleUntyped>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: NamedAsset> AssetFolder<T> {
+    /// Starts loading every asset in a folder under `assets`.
+    pub fn load(asset_server: &AssetServer, path: &str) -> Self {
+        let handles = asset_server
+            .load_folder(path)
+            .unwrap_or_else(|err| panic!("Could not load the '{path}' folder: {err}"));
+
+        Self {
+            handles,
+            marker: PhantomData,
+        }
+    }
+
+    /// Whether every asset has been loaded, or failed to.
+    pub fn ready(&self, asset_server: &AssetServer) -> bool {
+        matches!(
+            asset_server.get_group_load_state(self.handles.iter().map(|handle| handle.id)),
+            LoadState::Loaded | LoadState::Failed
+        )
+    }
+
+    /// The asset with a name.
+    pub fn find<'a>(&self, name: &str, assets: &'a Assets<T>) -> Option<(Handle<T>, &'a T)> {
+        self.handles.iter().find_map(|handle| {
+            let handle = handle.clone().typed::<T>();
+            let asset = assets.get(&handle)?;
+
+            (asset.name() == name).then_some((handle, asset))
+        })
+    }
+
+    /// The names of every loaded asset.
+    pub fn names<'a>(&'a self, assets: &'a Assets<T>) -> impl Iterator<Item = &'a str> {
+        self.handles
+            .iter()
+            .filter_map(|handle| assets.get(&handle.clone().typed::<T>()))
+            .map(|asset| asset.name())
+    }
+}